@@ -0,0 +1,308 @@
+use crate::rotation::{Direction, Rotation};
+use crate::{CountRule, Dial};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Small splitmix64 generator, good enough for making test inputs
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // Number in 0..n, n must not be 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn direction(&mut self) -> Direction {
+        if self.below(2) == 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    }
+}
+
+// Hits on any of the dial's targets from a single rotation
+fn rotation_hits(dial: &Dial, pos: u64, rotation: Rotation, rule: CountRule) -> u64 {
+    match rule {
+        CountRule::EndOfRotation => {
+            let end = dial.turn(pos, rotation.direction, rotation.distance);
+            dial.targets.contains(&end) as u64
+        }
+        CountRule::EveryClick => dial
+            .targets
+            .iter()
+            .map(|target| {
+                dial.hits_closed_form(pos, rotation.direction, rotation.distance, *target)
+            })
+            .sum(),
+    }
+}
+
+// Clicks from pos to the closest target in the given direction
+fn nearest_target(dial: &Dial, pos: u64, direction: Direction) -> Option<u64> {
+    dial.targets
+        .iter()
+        .map(|target| dial.first_hit(pos, direction, *target))
+        .min()
+}
+
+// Random rotations from the dial's start that hit its targets exactly `hits` times.
+// Gives None if hits are wanted from a dial without targets.
+pub fn generate(dial: &Dial, rule: CountRule, hits: u64, rng: &mut Rng) -> Option<Vec<Rotation>> {
+    if hits > 0 && dial.targets.is_empty() {
+        return None;
+    }
+
+    let mut rotations = Vec::new();
+    let mut pos = dial.start;
+    let mut remaining = hits;
+
+    while remaining > 0 {
+        let direction = rng.direction();
+
+        // Half the moves head straight for a target so that the generator always makes progress,
+        // the other half are random and only kept if they don't overshoot
+        let distance = if rng.below(2) == 0 {
+            let nearest = nearest_target(dial, pos, direction)?;
+
+            if rule == CountRule::EveryClick {
+                let max_revolutions = (remaining - 1) / dial.targets.len() as u64;
+                let revolutions = rng.below(max_revolutions.min(3) + 1);
+                nearest.saturating_add(revolutions.saturating_mul(dial.size))
+            } else {
+                nearest
+            }
+        } else {
+            rng.below(dial.size.saturating_mul(3))
+        };
+
+        let rotation = Rotation {
+            direction,
+            distance,
+        };
+        let rotation_hits = rotation_hits(dial, pos, rotation, rule);
+
+        if rotation_hits <= remaining {
+            remaining -= rotation_hits;
+            pos = dial.turn(pos, direction, distance);
+            rotations.push(rotation);
+        }
+    }
+
+    Some(rotations)
+}
+
+// Shortest sequence of rotations from the dial's start that hits its targets exactly `count` times.
+// Counting every click a single rotation is enough, counting rotation ends needs one per hit.
+// Gives None if it can't be done, for a dial without targets or a distance too large for u64.
+pub fn solve(dial: &Dial, rule: CountRule, count: u64) -> Option<Vec<Rotation>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+
+    if dial.targets.is_empty() {
+        return None;
+    }
+
+    match rule {
+        CountRule::EveryClick => {
+            let targets = dial.targets.len() as u64;
+            let revolutions = (count - 1) / targets;
+            let nth_target = ((count - 1) % targets) as usize;
+
+            // The count-th hit happens after some full revolutions plus the distance to the n-th closest target
+            let distance = |direction| {
+                let mut first_hits: Vec<u64> = dial
+                    .targets
+                    .iter()
+                    .map(|target| dial.first_hit(dial.start, direction, *target))
+                    .collect();
+                first_hits.sort();
+
+                revolutions
+                    .checked_mul(dial.size)?
+                    .checked_add(first_hits[nth_target])
+            };
+
+            let rotation = match (distance(Direction::Right), distance(Direction::Left)) {
+                (Some(right), Some(left)) if left < right => Rotation {
+                    direction: Direction::Left,
+                    distance: left,
+                },
+                (Some(right), _) => Rotation {
+                    direction: Direction::Right,
+                    distance: right,
+                },
+                (None, Some(left)) => Rotation {
+                    direction: Direction::Left,
+                    distance: left,
+                },
+                (None, None) => return None,
+            };
+
+            Some(vec![rotation])
+        }
+        CountRule::EndOfRotation => {
+            let mut rotations = Vec::new();
+            let mut pos = dial.start;
+
+            for _ in 0..count {
+                let right = nearest_target(dial, pos, Direction::Right)?;
+                let left = nearest_target(dial, pos, Direction::Left)?;
+
+                let rotation = if left < right {
+                    Rotation {
+                        direction: Direction::Left,
+                        distance: left,
+                    }
+                } else {
+                    Rotation {
+                        direction: Direction::Right,
+                        distance: right,
+                    }
+                };
+
+                pos = dial.turn(pos, rotation.direction, rotation.distance);
+                rotations.push(rotation);
+            }
+
+            Some(rotations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CountMode, count_zero_positions};
+
+    fn count(dial: &Dial, rotations: &[Rotation], rule: CountRule) -> u64 {
+        let click = count_zero_positions(rotations.iter().copied(), dial, CountMode::Click, false);
+        let closed_form = count_zero_positions(
+            rotations.iter().copied(),
+            dial,
+            CountMode::ClosedForm,
+            false,
+        );
+
+        assert_eq!(click, closed_form);
+
+        match rule {
+            CountRule::EndOfRotation => closed_form.end_hits.values().sum(),
+            CountRule::EveryClick => closed_form.click_hits.values().sum(),
+        }
+    }
+
+    #[test]
+    fn seeded_generation_is_repeatable() {
+        let dial = Dial::default();
+        let first = generate(&dial, CountRule::EveryClick, 20, &mut Rng::new(7));
+        let second = generate(&dial, CountRule::EveryClick, 20, &mut Rng::new(7));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn generated_inputs_have_the_requested_count() {
+        let dials = [
+            Dial::default(),
+            Dial::new(10, 3, &[0, 5, 9]),
+            Dial::new(1, 0, &[0]),
+        ];
+
+        for dial in &dials {
+            for rule in [CountRule::EndOfRotation, CountRule::EveryClick] {
+                for seed in 0..20 {
+                    let hits = seed * 3;
+                    let rotations = generate(dial, rule, hits, &mut Rng::new(seed)).unwrap();
+
+                    assert_eq!(count(dial, &rotations, rule), hits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generate_without_targets() {
+        let dial = Dial::new(10, 0, &[]);
+
+        assert_eq!(
+            generate(&dial, CountRule::EveryClick, 0, &mut Rng::new(1)),
+            Some(vec![])
+        );
+        assert_eq!(
+            generate(&dial, CountRule::EveryClick, 1, &mut Rng::new(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn solve_every_click() {
+        let dial = Dial::default();
+
+        // 50 clicks either way reaches 0, then 100 more for each extra hit
+        let rotations = solve(&dial, CountRule::EveryClick, 3).unwrap();
+        assert_eq!(rotations.len(), 1);
+        assert_eq!(rotations[0].to_string(), "R250");
+        assert_eq!(count(&dial, &rotations, CountRule::EveryClick), 3);
+
+        let dial = Dial::new(10, 2, &[0, 5]);
+        let rotations = solve(&dial, CountRule::EveryClick, 4).unwrap();
+        assert_eq!(rotations[0].to_string(), "L17");
+        assert_eq!(count(&dial, &rotations, CountRule::EveryClick), 4);
+    }
+
+    #[test]
+    fn solve_end_of_rotation() {
+        let dial = Dial::new(10, 2, &[0, 5]);
+        let rotations = solve(&dial, CountRule::EndOfRotation, 3).unwrap();
+
+        assert_eq!(rotations.len(), 3);
+        assert_eq!(count(&dial, &rotations, CountRule::EndOfRotation), 3);
+
+        // Starting on the only target means going all the way around each time
+        let dial = Dial::new(10, 0, &[0]);
+        let rotations = solve(&dial, CountRule::EndOfRotation, 2).unwrap();
+        let rotations: Vec<String> = rotations.iter().map(|r| r.to_string()).collect();
+
+        assert_eq!(rotations, vec!["R10", "R10"]);
+    }
+
+    #[test]
+    fn solve_impossible() {
+        assert_eq!(
+            solve(&Dial::new(10, 0, &[]), CountRule::EndOfRotation, 1),
+            None
+        );
+        assert_eq!(
+            solve(&Dial::default(), CountRule::EveryClick, u64::MAX),
+            None
+        );
+        assert_eq!(
+            solve(&Dial::default(), CountRule::EveryClick, 0),
+            Some(vec![])
+        );
+    }
+}
@@ -63,76 +63,507 @@
 // Be careful: if the dial were pointing at 50, a single rotation like R1000 would cause the dial to point at 0 ten times before returning back to 50!
 //
 
+mod generator;
+mod odometer;
+mod rotation;
+mod script;
+mod stats;
+
+use generator::{Rng, generate, solve};
+use odometer::Odometer;
+use rotation::{Direction, Rotation, RotationReader};
+use script::{expand_script, parse_script};
+use stats::DialStats;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
 
-fn count_zero_positions(input: String, verbose: bool) -> i32 {
-    let mut times_at_zero = 0;
-    let mut dial_pos = 50;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CountMode {
+    Click,
+    ClosedForm,
+}
+
+#[derive(Debug, Clone)]
+struct Dial {
+    size: u64,
+    start: u64,
+    targets: BTreeSet<u64>,
+}
 
-    for line in input.lines() {
-        if line.is_empty() {
-            continue;
+impl Dial {
+    pub fn new(size: u64, start: u64, targets: &[u64]) -> Dial {
+        assert!(size > 0, "dial size must be at least 1");
+        assert!(
+            start < size,
+            "start {} is outside dial of size {}",
+            start,
+            size
+        );
+
+        for target in targets {
+            assert!(
+                *target < size,
+                "target {} is outside dial of size {}",
+                target,
+                size
+            );
         }
 
-        let dir = if line.starts_with('L') {
-            -1
-        } else if line.starts_with('R') {
-            1
+        Dial {
+            size,
+            start,
+            targets: targets.iter().copied().collect(),
+        }
+    }
+
+    // Position after turning `rotation` clicks from `pos`
+    pub fn turn(&self, pos: u64, dir: Direction, rotation: u64) -> u64 {
+        let rotation = rotation % self.size;
+
+        match dir {
+            Direction::Right if rotation >= self.size - pos => rotation - (self.size - pos),
+            Direction::Right => pos + rotation,
+            Direction::Left if rotation > pos => self.size - (rotation - pos),
+            Direction::Left => pos - rotation,
+        }
+    }
+
+    // Clicks until target is first reached from pos, always at least 1
+    pub fn first_hit(&self, pos: u64, dir: Direction, target: u64) -> u64 {
+        let clicks_to_target = match dir {
+            Direction::Right if target >= pos => target - pos,
+            Direction::Right => self.size - (pos - target),
+            Direction::Left if pos >= target => pos - target,
+            Direction::Left => self.size - (target - pos),
+        };
+
+        // Already at the target means a full revolution is needed before hitting it again
+        if clicks_to_target == 0 {
+            self.size
+        } else {
+            clicks_to_target
+        }
+    }
+
+    // Number of times a rotation from pos lands on target, without stepping through every click.
+    pub fn hits_closed_form(&self, pos: u64, dir: Direction, rotation: u64, target: u64) -> u64 {
+        let first_hit = self.first_hit(pos, dir, target);
+
+        if rotation < first_hit {
+            0
+        } else {
+            (rotation - first_hit) / self.size + 1
+        }
+    }
+
+    // Turning the shortest way to target, to the right when both ways are equally long
+    pub fn shortest_turn(&self, pos: u64, target: u64) -> Rotation {
+        let (right, left) = if target >= pos {
+            (target - pos, self.size - (target - pos))
         } else {
-            panic!("Unsupported input: {}", line);
+            (self.size - (pos - target), pos - target)
         };
 
-        let rotation: i32 = line[1..].parse().unwrap();
+        if right <= left {
+            Rotation {
+                direction: Direction::Right,
+                distance: right,
+            }
+        } else {
+            Rotation {
+                direction: Direction::Left,
+                distance: left,
+            }
+        }
+    }
 
-        if verbose {
-            println!("Dial pos: {}, Rotation: {}", dial_pos, dir * rotation);
+    // Wrapping right means landing on 0, wrapping left means landing on the highest position
+    pub fn wraps_closed_form(&self, pos: u64, dir: Direction, rotation: u64) -> u64 {
+        match dir {
+            Direction::Right => self.hits_closed_form(pos, dir, rotation, 0),
+            Direction::Left => self.hits_closed_form(pos, dir, rotation, self.size - 1),
         }
+    }
+}
+
+impl Default for Dial {
+    // The dial from the puzzle: 0-99, starting at 50, counting 0
+    fn default() -> Dial {
+        Dial::new(100, 50, &[0])
+    }
+}
 
-        for _ in 0..rotation {
-            dial_pos += dir;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CountRule {
+    EndOfRotation,
+    EveryClick,
+}
 
-            if dial_pos >= 100 {
-                dial_pos = 0;
+// Both passwords for each target, part 1 only counts where a rotation ends, part 2 counts every click
+#[derive(Debug, Clone, PartialEq)]
+struct DialResult {
+    end_hits: BTreeMap<u64, u64>,
+    click_hits: BTreeMap<u64, u64>,
+    final_pos: u64,
+}
 
-                if verbose {
-                    println!("overflowed");
-                }
-            } else if dial_pos < 0 {
-                dial_pos += 100;
+// What happens while the dial is turning. Closed form counting reports wraps and hits once per
+// rotation with their total count, click counting reports each one as it happens with count 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DialEvent {
+    RotationStarted {
+        position: u64,
+        direction: Direction,
+        distance: u64,
+    },
+    Wrapped {
+        direction: Direction,
+        count: u64,
+    },
+    HitTarget {
+        target: u64,
+        count: u64,
+    },
+    RotationFinished {
+        position: u64,
+    },
+}
+
+fn print_event(event: &DialEvent) {
+    match event {
+        DialEvent::RotationStarted {
+            position,
+            direction,
+            distance,
+        } => println!(
+            "Dial pos: {}, Rotation: {:?} {}",
+            position, direction, distance
+        ),
+        DialEvent::Wrapped {
+            direction: Direction::Right,
+            count,
+        } => println!("overflowed {} times", count),
+        DialEvent::Wrapped {
+            direction: Direction::Left,
+            count,
+        } => println!("underflowed {} times", count),
+        DialEvent::HitTarget { target, count } => println!("At {} {} times!", target, count),
+        DialEvent::RotationFinished { position } => println!("Ended at {}", position),
+    }
+}
+
+fn simulate<I, F>(rotations: I, dial: &Dial, mode: CountMode, mut on_event: F) -> DialResult
+where
+    I: IntoIterator<Item = Rotation>,
+    F: FnMut(DialEvent),
+{
+    let mut end_hits: BTreeMap<u64, u64> = dial.targets.iter().map(|t| (*t, 0)).collect();
+    let mut click_hits = end_hits.clone();
+    let mut dial_pos = dial.start;
+
+    for Rotation {
+        direction: dir,
+        distance: rotation,
+    } in rotations
+    {
+        on_event(DialEvent::RotationStarted {
+            position: dial_pos,
+            direction: dir,
+            distance: rotation,
+        });
+
+        if mode == CountMode::ClosedForm {
+            let wraps = dial.wraps_closed_form(dial_pos, dir, rotation);
+            if wraps > 0 {
+                on_event(DialEvent::Wrapped {
+                    direction: dir,
+                    count: wraps,
+                });
+            }
 
-                if verbose {
-                    println!("underflowed");
+            for (target, count) in click_hits.iter_mut() {
+                let hits = dial.hits_closed_form(dial_pos, dir, rotation, *target);
+                *count += hits;
+
+                if hits > 0 {
+                    on_event(DialEvent::HitTarget {
+                        target: *target,
+                        count: hits,
+                    });
                 }
             }
 
-            if dial_pos == 0 {
-                times_at_zero += 1;
+            dial_pos = dial.turn(dial_pos, dir, rotation);
+        } else {
+            for _ in 0..rotation {
+                match dir {
+                    Direction::Right => {
+                        dial_pos += 1;
+
+                        if dial_pos == dial.size {
+                            dial_pos = 0;
+                            on_event(DialEvent::Wrapped {
+                                direction: dir,
+                                count: 1,
+                            });
+                        }
+                    }
+                    Direction::Left => {
+                        if dial_pos == 0 {
+                            dial_pos = dial.size;
+                            on_event(DialEvent::Wrapped {
+                                direction: dir,
+                                count: 1,
+                            });
+                        }
+
+                        dial_pos -= 1;
+                    }
+                }
 
-                if verbose {
-                    println!("At zero!")
+                if let Some(count) = click_hits.get_mut(&dial_pos) {
+                    *count += 1;
+                    on_event(DialEvent::HitTarget {
+                        target: dial_pos,
+                        count: 1,
+                    });
                 }
             }
         }
+
+        if let Some(count) = end_hits.get_mut(&dial_pos) {
+            *count += 1;
+        }
+
+        on_event(DialEvent::RotationFinished { position: dial_pos });
+    }
+
+    DialResult {
+        end_hits,
+        click_hits,
+        final_pos: dial_pos,
     }
+}
+
+fn count_zero_positions<I: IntoIterator<Item = Rotation>>(
+    rotations: I,
+    dial: &Dial,
+    mode: CountMode,
+    verbose: bool,
+) -> DialResult {
+    simulate(rotations, dial, mode, |event| {
+        if verbose {
+            print_event(&event);
+        }
+    })
+}
 
-    times_at_zero
+// Streaming stops at the first bad line, which is reported instead of a result
+fn stream_failed<R>(reader: &Option<RotationReader<R>>) -> bool {
+    match reader.as_ref().and_then(|reader| reader.error.as_ref()) {
+        Some(err) => {
+            println!("error reading input: {}", err);
+            true
+        }
+        None => false,
+    }
 }
 
 fn main() {
-    let input = match fs::read_to_string("data/input") {
-        Ok(input) => input,
-        Err(err) => panic!("File not found {}", err),
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .map_or("data/input", |arg| arg.as_str());
+
+    // Generated inputs and solutions are printed in the input format
+    let generate_hits = args.iter().find_map(|arg| arg.strip_prefix("--generate="));
+    let solve_hits = args.iter().find_map(|arg| arg.strip_prefix("--solve="));
+
+    if generate_hits.is_some() || solve_hits.is_some() {
+        let rule = if args.iter().any(|arg| arg == "--rule=end") {
+            CountRule::EndOfRotation
+        } else {
+            CountRule::EveryClick
+        };
+
+        let hits = match generate_hits.or(solve_hits).unwrap().parse() {
+            Ok(hits) => hits,
+            Err(err) => {
+                println!("error parsing hit count: {}", err);
+                return;
+            }
+        };
+
+        let rotations = if generate_hits.is_some() {
+            let mut rng = match args.iter().find_map(|arg| arg.strip_prefix("--seed=")) {
+                Some(seed) => match seed.parse() {
+                    Ok(seed) => Rng::new(seed),
+                    Err(err) => {
+                        println!("error parsing seed: {}", err);
+                        return;
+                    }
+                },
+                None => Rng::from_time(),
+            };
+
+            generate(&Dial::default(), rule, hits, &mut rng)
+        } else {
+            solve(&Dial::default(), rule, hits)
+        };
+
+        match rotations {
+            Some(rotations) => {
+                for rotation in rotations {
+                    println!("{}", rotation);
+                }
+            }
+            None => println!("no rotations give {} hits", hits),
+        }
+        return;
+    }
+
+    // A path of - reads from stdin
+    let mut input: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match fs::File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => panic!("File not found {}", err),
+        }
+    };
+
+    // Stepping click by click is kept around for debugging the closed form
+    let mode = if args.iter().any(|arg| arg == "--click") {
+        CountMode::Click
+    } else {
+        CountMode::ClosedForm
+    };
+
+    let verbose = args.iter().any(|arg| arg == "--verbose");
+    let dial = Dial::default();
+
+    // Hold the parsed script or the input reader while the rotations are borrowing from them
+    let script;
+    let mut reader = None;
+
+    let rotations: Box<dyn Iterator<Item = Rotation>> = if args.iter().any(|arg| arg == "--script")
+    {
+        // Scripts are small enough to read in one go
+        let mut text = String::new();
+
+        if let Err(err) = input.read_to_string(&mut text) {
+            println!("error reading script: {}", err);
+            return;
+        }
+
+        script = match parse_script(&text) {
+            Ok(script) => script,
+            Err(err) => {
+                println!("error parsing script: {}", err);
+                return;
+            }
+        };
+
+        match expand_script(&script, &dial) {
+            Ok(rotations) => Box::new(rotations),
+            Err(err) => {
+                println!("error in script: {}", err);
+                return;
+            }
+        }
+    } else {
+        Box::new(reader.insert(RotationReader::new(input)))
     };
 
-    let dial_pos = count_zero_positions(input, false);
+    // Extra dials chained after the puzzle dial, e.g. --dials=10,10
+    if let Some(sizes) = args.iter().find_map(|arg| arg.strip_prefix("--dials=")) {
+        let mut dials = vec![dial.clone()];
+
+        for size in sizes.split(',') {
+            match size.parse() {
+                Ok(0) => {
+                    println!("dial size must be at least 1");
+                    return;
+                }
+                Ok(size) => dials.push(Dial::new(size, 0, &[0])),
+                Err(err) => {
+                    println!("error parsing dial size {}: {}", size, err);
+                    return;
+                }
+            }
+        }
+
+        let odometer = Odometer::new(dials).run(rotations);
+
+        if stream_failed(&reader) {
+            return;
+        }
+
+        println!("lock state: {}", odometer);
+
+        for (i, hits) in odometer.hits.iter().enumerate() {
+            for (target, count) in hits {
+                println!("dial {}, clicks landing on {}: {}", i, target, count);
+            }
+        }
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--stats") {
+        let mut stats = DialStats::new(&dial);
+
+        simulate(rotations, &dial, mode, |event| {
+            if verbose {
+                print_event(&event);
+            }
+            stats.record(&event);
+        });
 
-    println!("dial pos: {}", dial_pos);
+        if stream_failed(&reader) {
+            return;
+        }
+
+        print!("{}", stats);
+        return;
+    }
+
+    let result = count_zero_positions(rotations, &dial, mode, verbose);
+
+    if stream_failed(&reader) {
+        return;
+    }
+
+    for (target, count) in &result.end_hits {
+        println!("part 1, rotations ending at {}: {}", target, count);
+    }
+
+    for (target, count) in &result.click_hits {
+        println!("part 2, clicks landing on {}: {}", target, count);
+    }
+
+    println!("final dial pos: {}", result.final_pos);
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    // Runs both counting modes and checks that they agree
+    fn count_both_modes_on(input: &str, dial: &Dial) -> DialResult {
+        let rotations = RotationReader::new(input.as_bytes()).collect::<Vec<_>>();
+        let click = count_zero_positions(rotations.iter().copied(), dial, CountMode::Click, true);
+        let closed_form =
+            count_zero_positions(rotations.iter().copied(), dial, CountMode::ClosedForm, true);
+        assert_eq!(click, closed_form);
+        closed_form
+    }
+
+    fn count_both_modes(input: &str) -> u64 {
+        count_both_modes_on(input, &Dial::default()).click_hits[&0]
+    }
+
     #[test]
     fn example_input() {
         let input = match fs::read_to_string("data/example_input") {
@@ -140,49 +571,247 @@ mod test {
             Err(err) => panic!("File not found {}", err),
         };
 
-        let dial_pos = count_zero_positions(input, true);
+        let dial_pos = count_both_modes(&input);
 
         assert_eq!(dial_pos, 6);
     }
 
     #[test]
     fn part_2_r1000() {
-        let input = "R1000\n".to_string();
+        let input = "R1000\n";
 
-        let dial_pos = count_zero_positions(input, true);
+        let dial_pos = count_both_modes(input);
 
         assert_eq!(dial_pos, 10);
     }
 
     #[test]
     fn part_2_l150() {
-        let input = "L150\n".to_string();
-        let dial_pos = count_zero_positions(input, true);
+        let input = "L150\n";
+        let dial_pos = count_both_modes(input);
 
         assert_eq!(dial_pos, 2);
     }
 
     #[test]
     fn part_2_l50_l100() {
-        let input = "L50\nL100\n".to_string();
-        let dial_pos = count_zero_positions(input, true);
+        let input = "L50\nL100\n";
+        let dial_pos = count_both_modes(input);
 
         assert_eq!(dial_pos, 2);
     }
 
     #[test]
     fn part_2_l50_l50() {
-        let input = "L50\nL50\n".to_string();
-        let dial_pos = count_zero_positions(input, true);
+        let input = "L50\nL50\n";
+        let dial_pos = count_both_modes(input);
 
         assert_eq!(dial_pos, 1);
     }
 
     #[test]
     fn part_2_general() {
-        let input = "R50\nR100\n".to_string();
-        let dial_pos = count_zero_positions(input, true);
+        let input = "R50\nR100\n";
+        let dial_pos = count_both_modes(input);
 
         assert_eq!(dial_pos, 2);
     }
+
+    #[test]
+    fn closed_form_matches_click_for_all_starts() {
+        for start in 0..100 {
+            for rotation in [0, 1, 99, 100, 101, 199, 200, 250] {
+                for dir in ["L", "R"] {
+                    // Move to the start position first, then do the rotation under test
+                    let input = format!("R{}\n{}{}\n", (start + 50) % 100, dir, rotation);
+                    count_both_modes(&input);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closed_form_large_rotation() {
+        let dial = Dial::default();
+
+        let rotations = RotationReader::new("R2000000000\n".as_bytes()).collect::<Vec<_>>();
+        let result = count_zero_positions(
+            rotations.iter().copied(),
+            &dial,
+            CountMode::ClosedForm,
+            false,
+        );
+
+        assert_eq!(result.click_hits[&0], 20000000);
+
+        let rotations =
+            RotationReader::new(format!("L{}\n", u64::MAX).as_bytes()).collect::<Vec<_>>();
+        let result = count_zero_positions(
+            rotations.iter().copied(),
+            &dial,
+            CountMode::ClosedForm,
+            false,
+        );
+
+        assert_eq!(result.click_hits[&0], (u64::MAX - 50) / 100 + 1);
+    }
+
+    #[test]
+    fn example_input_both_parts() {
+        let input = fs::read_to_string("data/example_input").unwrap();
+        let result = count_both_modes_on(&input, &Dial::default());
+
+        assert_eq!(result.end_hits[&0], 3);
+        assert_eq!(result.click_hits[&0], 6);
+        assert_eq!(result.final_pos, 32);
+    }
+
+    #[test]
+    fn full_revolution_ends_on_start_target() {
+        // R100 from 0 only reaches 0 on its last click, so both rules count it once
+        let result = count_both_modes_on("L50\nR100\nR1\n", &Dial::default());
+
+        assert_eq!(result.end_hits[&0], 2);
+        assert_eq!(result.click_hits[&0], 2);
+        assert_eq!(result.final_pos, 1);
+    }
+
+    #[test]
+    fn click_events() {
+        let dial = Dial::new(10, 8, &[0]);
+        let mut events = Vec::new();
+
+        let rotations = RotationReader::new("R3\nL2\n".as_bytes()).collect::<Vec<_>>();
+        let result = simulate(rotations, &dial, CountMode::Click, |event| {
+            events.push(event)
+        });
+
+        assert_eq!(
+            events,
+            vec![
+                DialEvent::RotationStarted {
+                    position: 8,
+                    direction: Direction::Right,
+                    distance: 3
+                },
+                DialEvent::Wrapped {
+                    direction: Direction::Right,
+                    count: 1
+                },
+                DialEvent::HitTarget {
+                    target: 0,
+                    count: 1
+                },
+                DialEvent::RotationFinished { position: 1 },
+                DialEvent::RotationStarted {
+                    position: 1,
+                    direction: Direction::Left,
+                    distance: 2
+                },
+                DialEvent::HitTarget {
+                    target: 0,
+                    count: 1
+                },
+                DialEvent::Wrapped {
+                    direction: Direction::Left,
+                    count: 1
+                },
+                DialEvent::RotationFinished { position: 9 },
+            ]
+        );
+        assert_eq!(result.click_hits[&0], 2);
+    }
+
+    #[test]
+    fn event_totals_match_between_modes() {
+        let input = fs::read_to_string("data/example_input").unwrap();
+        let rotations = RotationReader::new(input.as_bytes()).collect::<Vec<_>>();
+        let dial = Dial::default();
+
+        for mode in [CountMode::Click, CountMode::ClosedForm] {
+            let mut left_wraps = 0;
+            let mut right_wraps = 0;
+            let mut hits = 0;
+            let mut finished = Vec::new();
+
+            simulate(
+                rotations.iter().copied(),
+                &dial,
+                mode,
+                |event| match event {
+                    DialEvent::Wrapped {
+                        direction: Direction::Left,
+                        count,
+                    } => left_wraps += count,
+                    DialEvent::Wrapped {
+                        direction: Direction::Right,
+                        count,
+                    } => right_wraps += count,
+                    DialEvent::HitTarget { count, .. } => hits += count,
+                    DialEvent::RotationFinished { position } => finished.push(position),
+                    DialEvent::RotationStarted { .. } => {}
+                },
+            );
+
+            assert_eq!(left_wraps, 4);
+            assert_eq!(right_wraps, 2);
+            assert_eq!(hits, 6);
+            assert_eq!(finished, vec![82, 52, 0, 95, 55, 0, 99, 0, 14, 32]);
+        }
+    }
+
+    #[test]
+    fn streamed_input_matches_whole_input() {
+        let file = fs::File::open("data/input").unwrap();
+        let mut reader = RotationReader::new(BufReader::new(file));
+
+        let streamed =
+            count_zero_positions(&mut reader, &Dial::default(), CountMode::ClosedForm, false);
+        let input = fs::read_to_string("data/input").unwrap();
+        let whole = count_zero_positions(
+            RotationReader::new(input.as_bytes()).collect::<Vec<_>>(),
+            &Dial::default(),
+            CountMode::ClosedForm,
+            false,
+        );
+
+        assert!(reader.error.is_none());
+        assert_eq!(streamed, whole);
+    }
+
+    #[test]
+    fn custom_dial_multiple_targets() {
+        let dial = Dial::new(10, 0, &[0, 5]);
+
+        // 0 -> 7 passes 5, 7 -> 2 passes 0, 2 -> 5 passes 0 and lands on 5, 5 -> 5 passes both
+        let hits = count_both_modes_on("R7\nR5\nL7\nR10\n", &dial);
+
+        assert_eq!(hits.click_hits[&0], 3);
+        assert_eq!(hits.click_hits[&5], 3);
+    }
+
+    #[test]
+    fn custom_dial_closed_form_matches_click() {
+        let dial = Dial::new(7, 3, &[0, 3, 6]);
+
+        for rotation in 0..30 {
+            for dir in ["L", "R"] {
+                count_both_modes_on(&format!("R2\n{}{}\nL4\n", dir, rotation), &dial);
+            }
+        }
+    }
+
+    #[test]
+    fn single_position_dial() {
+        let dial = Dial::new(1, 0, &[0]);
+        let hits = count_both_modes_on("R3\nL4\n", &dial);
+
+        assert_eq!(hits.click_hits[&0], 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn target_outside_dial() {
+        Dial::new(10, 0, &[10]);
+    }
 }
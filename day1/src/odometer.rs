@@ -0,0 +1,179 @@
+use crate::Dial;
+use crate::rotation::Rotation;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Dials chained like an odometer. Rotations turn the first dial, and every time a dial wraps
+// around the next dial is moved one click in the same direction.
+#[derive(Debug, Clone)]
+pub struct Odometer {
+    dials: Vec<Dial>,
+    pub positions: Vec<u64>,
+    // Every-click hits on each dial's targets
+    pub hits: Vec<BTreeMap<u64, u64>>,
+}
+
+impl Odometer {
+    pub fn new(dials: Vec<Dial>) -> Odometer {
+        assert!(!dials.is_empty(), "odometer needs at least one dial");
+
+        Odometer {
+            positions: dials.iter().map(|dial| dial.start).collect(),
+            hits: dials
+                .iter()
+                .map(|dial| dial.targets.iter().map(|t| (*t, 0)).collect())
+                .collect(),
+            dials,
+        }
+    }
+
+    pub fn rotate(&mut self, rotation: Rotation) {
+        let dir = rotation.direction;
+        let mut distance = rotation.distance;
+
+        for (i, dial) in self.dials.iter().enumerate() {
+            if distance == 0 {
+                break;
+            }
+
+            let pos = self.positions[i];
+
+            for (target, count) in self.hits[i].iter_mut() {
+                *count += dial.hits_closed_form(pos, dir, distance, *target);
+            }
+
+            self.positions[i] = dial.turn(pos, dir, distance);
+
+            // The wraps of this dial are the clicks of the next one
+            distance = dial.wraps_closed_form(pos, dir, distance);
+        }
+    }
+
+    pub fn run<I: IntoIterator<Item = Rotation>>(mut self, rotations: I) -> Odometer {
+        for rotation in rotations {
+            self.rotate(rotation);
+        }
+
+        self
+    }
+}
+
+// Shown like an odometer, most significant (last) dial first
+impl fmt::Display for Odometer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let positions: Vec<String> = self
+            .positions
+            .iter()
+            .rev()
+            .map(|pos| pos.to_string())
+            .collect();
+
+        write!(f, "{}", positions.join("-"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rotation::{Direction, RotationReader};
+    use crate::{CountMode, DialEvent, simulate};
+
+    // Dials of the given sizes, all starting at and counting 0
+    fn from_sizes(sizes: &[u64]) -> Odometer {
+        Odometer::new(sizes.iter().map(|size| Dial::new(*size, 0, &[0])).collect())
+    }
+
+    // Reference implementation, turns each dial click by click and feeds its wrap events to the next
+    fn click_by_click(sizes: &[u64], input: &str) -> (Vec<u64>, Vec<u64>) {
+        let mut positions = vec![0; sizes.len()];
+        let mut zeros = vec![0; sizes.len()];
+
+        for rotation in RotationReader::new(input.as_bytes()).collect::<Vec<_>>() {
+            let mut moves = vec![rotation];
+
+            for (i, size) in sizes.iter().enumerate() {
+                let dial = Dial::new(*size, positions[i], &[0]);
+                let mut wraps = Vec::new();
+
+                let result = simulate(moves, &dial, CountMode::Click, |event| {
+                    if let DialEvent::Wrapped { direction, .. } = event {
+                        wraps.push(Rotation {
+                            direction,
+                            distance: 1,
+                        });
+                    }
+                });
+
+                positions[i] = result.final_pos;
+                zeros[i] += result.click_hits[&0];
+                moves = wraps;
+            }
+        }
+
+        (positions, zeros)
+    }
+
+    #[test]
+    fn counts_like_an_odometer() {
+        let mut odometer = from_sizes(&[10, 10, 10]);
+
+        odometer.rotate(Rotation {
+            direction: Direction::Right,
+            distance: 1234,
+        });
+
+        assert_eq!(odometer.positions, vec![4, 3, 2]);
+        assert_eq!(odometer.to_string(), "2-3-4");
+
+        odometer.rotate(Rotation {
+            direction: Direction::Left,
+            distance: 1235,
+        });
+
+        assert_eq!(odometer.positions, vec![9, 9, 9]);
+    }
+
+    #[test]
+    fn zero_counts_per_dial() {
+        let rotations = RotationReader::new("R25\nL30\n".as_bytes()).collect::<Vec<_>>();
+        let odometer = from_sizes(&[10, 3]).run(rotations);
+
+        // Dial 0 passes 0 twice going up and three times coming back down.
+        // Dial 1 goes 0 -> 1 -> 2 and back down to 2 -> 1 -> 0 -> 2
+        assert_eq!(odometer.positions, vec![5, 2]);
+        assert_eq!(odometer.hits[0][&0], 5);
+        assert_eq!(odometer.hits[1][&0], 1);
+    }
+
+    #[test]
+    fn matches_click_by_click() {
+        let sizes = [7, 3, 4];
+        let input = "R50\nL13\nR3\nL200\nR1\nL0\nR84\n";
+
+        let odometer =
+            from_sizes(&sizes).run(RotationReader::new(input.as_bytes()).collect::<Vec<_>>());
+        let (positions, zeros) = click_by_click(&sizes, input);
+
+        assert_eq!(odometer.positions, positions);
+        assert_eq!(
+            odometer
+                .hits
+                .iter()
+                .map(|hits| hits[&0])
+                .collect::<Vec<_>>(),
+            zeros
+        );
+    }
+
+    #[test]
+    fn single_dial_matches_simulation() {
+        let input = std::fs::read_to_string("data/example_input").unwrap();
+        let rotations = RotationReader::new(input.as_bytes()).collect::<Vec<_>>();
+
+        let odometer = Odometer::new(vec![Dial::default()]).run(rotations.iter().copied());
+        let result = simulate(rotations, &Dial::default(), CountMode::ClosedForm, |_| {});
+
+        assert_eq!(odometer.positions, vec![result.final_pos]);
+        assert_eq!(odometer.hits[0], result.click_hits);
+    }
+}
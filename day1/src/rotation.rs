@@ -0,0 +1,303 @@
+use std::fmt;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rotation {
+    pub direction: Direction,
+    pub distance: u64,
+}
+
+// Written back in the input format, e.g. L68
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        };
+
+        write!(f, "{}{}", dir, self.distance)
+    }
+}
+
+// Line and column are 1-based, and point at the start of the offending text
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationParseError {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RotationParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {} '{}'",
+            self.line, self.column, self.reason, self.text
+        )
+    }
+}
+
+impl std::error::Error for RotationParseError {}
+
+fn column_of(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count() + 1
+}
+
+// Parses a single line like "L68". Blank lines give Ok(None).
+pub fn parse_rotation(
+    line: &str,
+    line_number: usize,
+) -> Result<Option<Rotation>, RotationParseError> {
+    let error = |byte_offset: usize, text: &str, reason| RotationParseError {
+        line: line_number,
+        column: column_of(line, byte_offset),
+        text: text.to_string(),
+        reason,
+    };
+
+    let trimmed = line.trim_start();
+    let dir_offset = line.len() - trimmed.len();
+    let trimmed = trimmed.trim_end();
+
+    let mut chars = trimmed.chars();
+    let direction = match chars.next() {
+        None => return Ok(None),
+        Some('L' | 'l') => Direction::Left,
+        Some('R' | 'r') => Direction::Right,
+        Some(_) => return Err(error(dir_offset, trimmed, "unknown direction in")),
+    };
+
+    let rest = chars.as_str();
+    let distance_str = rest.trim_start();
+    let distance_offset = dir_offset + 1 + (rest.len() - distance_str.len());
+
+    if distance_str.is_empty() {
+        return Err(error(dir_offset, trimmed, "missing distance in"));
+    }
+
+    // parse() would also take a leading '+', a distance is only digits
+    let distance = distance_str
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| distance_str.parse().ok())
+        .flatten()
+        .ok_or_else(|| error(distance_offset, distance_str, "invalid distance"))?;
+
+    Ok(Some(Rotation {
+        direction,
+        distance,
+    }))
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Parse(RotationParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "{}", err),
+            ReadError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+// Reads rotations one line at a time from a file, stdin or anything else buffered, so the whole
+// input never has to be in memory. Iteration stops at the first bad line, which is kept in `error`.
+pub struct RotationReader<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+    pub error: Option<ReadError>,
+}
+
+impl<R: BufRead> RotationReader<R> {
+    pub fn new(reader: R) -> RotationReader<R> {
+        RotationReader {
+            reader,
+            line: String::new(),
+            line_number: 0,
+            error: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RotationReader<R> {
+    type Item = Rotation;
+
+    fn next(&mut self) -> Option<Rotation> {
+        while self.error.is_none() {
+            self.line.clear();
+
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line_number += 1;
+
+                    match parse_rotation(&self.line, self.line_number) {
+                        Ok(Some(rotation)) => return Some(rotation),
+                        Ok(None) => {}
+                        Err(err) => self.error = Some(ReadError::Parse(err)),
+                    }
+                }
+                Err(err) => self.error = Some(ReadError::Io(err)),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read_all(input: &str) -> Vec<Rotation> {
+        let mut reader = RotationReader::new(input.as_bytes());
+        let rotations = reader.by_ref().collect();
+
+        assert!(reader.error.is_none());
+        rotations
+    }
+
+    // The first bad line, as the reader keeps it
+    fn read_error(input: &str) -> RotationParseError {
+        let mut reader = RotationReader::new(input.as_bytes());
+        reader.by_ref().for_each(drop);
+
+        match reader.error {
+            Some(ReadError::Parse(err)) => err,
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_example() {
+        let rotations = read_all("L68\nR48\n\nl5\n  r60 \r\n");
+
+        assert_eq!(
+            rotations,
+            vec![
+                Rotation {
+                    direction: Direction::Left,
+                    distance: 68
+                },
+                Rotation {
+                    direction: Direction::Right,
+                    distance: 48
+                },
+                Rotation {
+                    direction: Direction::Left,
+                    distance: 5
+                },
+                Rotation {
+                    direction: Direction::Right,
+                    distance: 60
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn space_between_direction_and_distance() {
+        let rotation = parse_rotation("R 10", 1).unwrap();
+
+        assert_eq!(
+            rotation,
+            Some(Rotation {
+                direction: Direction::Right,
+                distance: 10
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_direction() {
+        let err = read_error("L1\n  X5\n");
+
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 3);
+        assert_eq!(err.text, "X5");
+    }
+
+    #[test]
+    fn missing_distance() {
+        let err = read_error("L1\nR2\nR\n");
+
+        assert_eq!(err.line, 3);
+        assert_eq!(err.column, 1);
+        assert_eq!(err.reason, "missing distance in");
+    }
+
+    #[test]
+    fn invalid_distance() {
+        let err = read_error("R 1x2\n");
+
+        assert_eq!(err.line, 1);
+        assert_eq!(err.column, 3);
+        assert_eq!(err.text, "1x2");
+        assert_eq!(err.to_string(), "line 1, column 3: invalid distance '1x2'");
+
+        let err = read_error("L-5\n");
+        assert_eq!(err.column, 2);
+
+        let err = read_error("R+5\n");
+        assert_eq!(err.column, 2);
+        assert_eq!(err.to_string(), "line 1, column 2: invalid distance '+5'");
+    }
+
+    #[test]
+    fn reader_matches_parse_rotation() {
+        let input = std::fs::read_to_string("data/input").unwrap();
+        let mut reader = RotationReader::new(input.as_bytes());
+        let by_line: Vec<Rotation> = input
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| parse_rotation(line, i + 1).unwrap())
+            .collect();
+
+        assert_eq!(reader.by_ref().collect::<Vec<_>>(), by_line);
+        assert!(reader.error.is_none());
+    }
+
+    #[test]
+    fn reader_from_file() {
+        let file = std::fs::File::open("data/example_input").unwrap();
+        let reader = RotationReader::new(io::BufReader::new(file));
+
+        assert_eq!(reader.count(), 10);
+    }
+
+    #[test]
+    fn reader_stops_at_first_error() {
+        let mut reader = RotationReader::new("L1\r\nR2\r\n\r\nQ3\nL4\n".as_bytes());
+
+        assert_eq!(reader.by_ref().count(), 2);
+        assert_eq!(reader.next(), None);
+
+        match reader.error {
+            Some(ReadError::Parse(err)) => assert_eq!((err.line, err.column), (4, 1)),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reader_reports_io_errors() {
+        // Not valid UTF-8, so read_line fails
+        let mut reader = RotationReader::new(&b"L1\n\xff\xfe\n"[..]);
+
+        assert_eq!(reader.by_ref().count(), 1);
+        assert!(matches!(reader.error, Some(ReadError::Io(_))));
+    }
+}
@@ -0,0 +1,389 @@
+// Lock scripts extend the plain rotation format with
+//
+//     # comments until the end of the line
+//     L5, R10          several rotations on one line
+//     3x(L5, R10)      repeat blocks, which can be nested
+//     =NN              turn the shortest way to position NN, to the right on a tie
+//
+// Everything expands to plain rotations, so the dial counts them like any other input.
+
+use crate::Dial;
+use crate::rotation::{Rotation, RotationParseError, parse_rotation};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Turn(Rotation),
+    Absolute {
+        position: u64,
+        line: usize,
+        column: usize,
+    },
+    Repeat {
+        times: u64,
+        body: Vec<Instruction>,
+    },
+}
+
+struct LineParser<'a> {
+    line: &'a str,
+    line_number: usize,
+    pos: usize,
+}
+
+impl<'a> LineParser<'a> {
+    fn column(&self, byte_offset: usize) -> usize {
+        self.line[..byte_offset].chars().count() + 1
+    }
+
+    // The offending text runs until the next separator
+    fn token_end(&self, start: usize) -> usize {
+        self.line[start..]
+            .find([',', '(', ')'])
+            .map_or(self.line.len(), |i| start + i)
+    }
+
+    fn error(&self, start: usize, reason: &'static str) -> RotationParseError {
+        RotationParseError {
+            line: self.line_number,
+            column: self.column(start),
+            text: self.line[start..self.token_end(start)].trim().to_string(),
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.line[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.line[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, c: char, reason: &'static str) -> Result<(), RotationParseError> {
+        self.skip_whitespace();
+
+        if self.peek() != Some(c) {
+            return Err(self.error(self.pos, reason));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<u64, RotationParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let digits = self.line[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.line.len() - start);
+
+        if digits == 0 {
+            return Err(self.error(start, "expected a number at"));
+        }
+
+        self.pos += digits;
+        self.line[start..self.pos]
+            .parse()
+            .map_err(|_| self.error(start, "number too large"))
+    }
+
+    fn list(&mut self) -> Result<Vec<Instruction>, RotationParseError> {
+        let mut instructions = vec![self.item()?];
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some(',') {
+                return Ok(instructions);
+            }
+
+            self.pos += 1;
+            instructions.push(self.item()?);
+        }
+    }
+
+    fn item(&mut self) -> Result<Instruction, RotationParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        match self.peek() {
+            Some('=') => {
+                self.pos += 1;
+                let position = self.number()?;
+
+                Ok(Instruction::Absolute {
+                    position,
+                    line: self.line_number,
+                    column: self.column(start),
+                })
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let times = self.number()?;
+                self.skip_whitespace();
+
+                if !matches!(self.peek(), Some('x' | 'X')) {
+                    return Err(self.error(self.pos, "expected 'x' after repeat count at"));
+                }
+
+                self.pos += 1;
+                self.expect('(', "expected '(' at")?;
+                let body = self.list()?;
+                self.expect(')', "expected ')' at")?;
+
+                Ok(Instruction::Repeat { times, body })
+            }
+            _ => {
+                // Plain rotations are parsed exactly like the simple format
+                let end = self.token_end(start);
+                let token = &self.line[start..end];
+                self.pos = end;
+
+                match parse_rotation(token, self.line_number) {
+                    Ok(Some(rotation)) => Ok(Instruction::Turn(rotation)),
+                    Ok(None) => Err(self.error(start, "expected a rotation at")),
+                    Err(mut err) => {
+                        err.column += self.column(start) - 1;
+                        Err(err)
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn parse_script(input: &str) -> Result<Vec<Instruction>, RotationParseError> {
+    let mut script = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut parser = LineParser {
+            line,
+            line_number: i + 1,
+            pos: 0,
+        };
+
+        script.extend(parser.list()?);
+        parser.skip_whitespace();
+
+        if parser.pos != line.len() {
+            return Err(parser.error(parser.pos, "unexpected text"));
+        }
+    }
+
+    Ok(script)
+}
+
+// Lazily expands a script, so large repeat counts don't need to fit in memory
+pub struct ScriptRotations<'a> {
+    dial: &'a Dial,
+    position: u64,
+    // Instructions being run, index of the next one and how many times they are still to run
+    stack: Vec<(&'a [Instruction], usize, u64)>,
+}
+
+impl<'a> Iterator for ScriptRotations<'a> {
+    type Item = Rotation;
+
+    fn next(&mut self) -> Option<Rotation> {
+        loop {
+            let (instructions, next, remaining) = self.stack.last_mut()?;
+
+            if *next == instructions.len() {
+                *remaining -= 1;
+
+                if *remaining == 0 {
+                    self.stack.pop();
+                } else {
+                    *next = 0;
+                }
+                continue;
+            }
+
+            let instruction = &instructions[*next];
+            *next += 1;
+
+            let rotation = match instruction {
+                Instruction::Turn(rotation) => *rotation,
+                Instruction::Absolute { position, .. } => {
+                    self.dial.shortest_turn(self.position, *position)
+                }
+                Instruction::Repeat { times, body } => {
+                    if *times > 0 {
+                        self.stack.push((body, 0, *times));
+                    }
+                    continue;
+                }
+            };
+
+            self.position = self
+                .dial
+                .turn(self.position, rotation.direction, rotation.distance);
+
+            return Some(rotation);
+        }
+    }
+}
+
+fn check_positions(script: &[Instruction], dial: &Dial) -> Result<(), RotationParseError> {
+    for instruction in script {
+        match instruction {
+            Instruction::Turn(_) => {}
+            Instruction::Absolute {
+                position,
+                line,
+                column,
+            } => {
+                if *position >= dial.size {
+                    return Err(RotationParseError {
+                        line: *line,
+                        column: *column,
+                        text: format!("={}", position),
+                        reason: "position outside dial",
+                    });
+                }
+            }
+            Instruction::Repeat { body, .. } => check_positions(body, dial)?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn expand_script<'a>(
+    script: &'a [Instruction],
+    dial: &'a Dial,
+) -> Result<ScriptRotations<'a>, RotationParseError> {
+    check_positions(script, dial)?;
+
+    Ok(ScriptRotations {
+        dial,
+        position: dial.start,
+        stack: vec![(script, 0, 1)],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rotation::RotationReader;
+    use crate::{CountMode, count_zero_positions};
+
+    fn expand(input: &str, dial: &Dial) -> Vec<Rotation> {
+        let script = parse_script(input).unwrap();
+        expand_script(&script, dial).unwrap().collect()
+    }
+
+    #[test]
+    fn plain_input_is_a_script() {
+        let input = std::fs::read_to_string("data/example_input").unwrap();
+
+        assert_eq!(
+            expand(&input, &Dial::default()),
+            RotationReader::new(input.as_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn comments_and_lists() {
+        let input = "# start\nL5, r10 # trailing\n\n   # only a comment\nR1\n";
+
+        assert_eq!(
+            expand(input, &Dial::default()),
+            RotationReader::new("L5\nR10\nR1\n".as_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn repeat_blocks() {
+        let dial = Dial::default();
+
+        assert_eq!(
+            expand("3x(L5,R10)", &dial),
+            RotationReader::new("L5\nR10\nL5\nR10\nL5\nR10\n".as_bytes()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            expand("2x( R1, 2X(L2) ), 0x(R100)", &dial),
+            RotationReader::new("R1\nL2\nL2\nR1\nL2\nL2\n".as_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn large_repeat_counts_lazily() {
+        let dial = Dial::default();
+        let script = parse_script("1000000000x(R100)").unwrap();
+        let rotations = expand_script(&script, &dial).unwrap();
+
+        assert_eq!(rotations.take(3).count(), 3);
+    }
+
+    #[test]
+    fn absolute_moves_take_the_shortest_way() {
+        let dial = Dial::default();
+
+        // 50 -> 20 is 30 to the left, 20 -> 90 is 30 to the left, 90 -> 10 is 20 to the right
+        assert_eq!(
+            expand("=20\n=90\n=10\n=60\n=60", &dial),
+            RotationReader::new("L30\nL30\nR20\nR50\nR0\n".as_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn script_counts_match_expanded_rotations() {
+        let dial = Dial::default();
+        let script = parse_script("=0\n2x(L150, =99, R1)\n").unwrap();
+        let rotations: Vec<Rotation> = expand_script(&script, &dial).unwrap().collect();
+
+        let result = count_zero_positions(
+            rotations.iter().copied(),
+            &dial,
+            CountMode::ClosedForm,
+            false,
+        );
+        let plain = RotationReader::new("R50\nL150\nR49\nR1\nL150\nR49\nR1\n".as_bytes())
+            .collect::<Vec<_>>();
+
+        assert_eq!(rotations, plain);
+        assert_eq!(result.click_hits[&0], 5);
+        assert_eq!(result.end_hits[&0], 3);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse_script("L1\n2x(L5,X3)\n").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 7));
+        assert_eq!(err.text, "X3");
+
+        let err = parse_script("2x(L5,R3\n").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 9));
+        assert_eq!(err.reason, "expected ')' at");
+
+        let err = parse_script("2(L5)\n").unwrap_err();
+        assert_eq!(err.column, 2);
+
+        let err = parse_script("= \n").unwrap_err();
+        assert_eq!(err.reason, "expected a number at");
+
+        let err = parse_script("L5,\n").unwrap_err();
+        assert_eq!(err.column, 4);
+
+        let err = parse_script("L5 R5\n").unwrap_err();
+        assert_eq!(err.column, 2);
+        assert_eq!(err.text, "5 R5");
+    }
+
+    #[test]
+    fn absolute_outside_dial() {
+        let dial = Dial::new(10, 0, &[0]);
+        let script = parse_script("R1\n  =10\n").unwrap();
+        let err = expand_script(&script, &dial).err().unwrap();
+
+        assert_eq!((err.line, err.column), (2, 3));
+        assert_eq!(err.reason, "position outside dial");
+    }
+}
@@ -0,0 +1,296 @@
+use crate::rotation::{Direction, Rotation};
+use crate::{Dial, DialEvent};
+use std::fmt;
+
+// Where the dial spends its time. Built from the simulation events, so it works the same
+// for click and closed form counting. The histograms have one entry per dial position.
+#[derive(Debug, Clone)]
+pub struct DialStats {
+    dial: Dial,
+    // Full revolutions visit every position once, so only the partial ones are counted per position
+    full_revolutions: u64,
+    partial_visits: Vec<u64>,
+    pub end_visits: Vec<u64>,
+    pub left_wraps: u64,
+    pub right_wraps: u64,
+    pub longest_rotation: Option<Rotation>,
+    // Most clicks between two consecutive target hits
+    pub longest_stretch: Option<u64>,
+    clicks_since_hit: Option<u64>,
+}
+
+impl DialStats {
+    pub fn new(dial: &Dial) -> DialStats {
+        let size = usize::try_from(dial.size).expect("dial too large for a histogram");
+
+        DialStats {
+            dial: dial.clone(),
+            full_revolutions: 0,
+            partial_visits: vec![0; size],
+            end_visits: vec![0; size],
+            left_wraps: 0,
+            right_wraps: 0,
+            longest_rotation: None,
+            longest_stretch: None,
+            clicks_since_hit: None,
+        }
+    }
+
+    pub fn record(&mut self, event: &DialEvent) {
+        match *event {
+            DialEvent::RotationStarted {
+                position,
+                direction,
+                distance,
+            } => {
+                self.count_clicks(position, direction, distance);
+                self.track_stretches(position, direction, distance);
+
+                if self.longest_rotation.is_none_or(|r| distance > r.distance) {
+                    self.longest_rotation = Some(Rotation {
+                        direction,
+                        distance,
+                    });
+                }
+            }
+            DialEvent::Wrapped {
+                direction: Direction::Left,
+                count,
+            } => self.left_wraps += count,
+            DialEvent::Wrapped {
+                direction: Direction::Right,
+                count,
+            } => self.right_wraps += count,
+            DialEvent::HitTarget { .. } => {}
+            DialEvent::RotationFinished { position } => self.end_visits[position as usize] += 1,
+        }
+    }
+
+    // Number of clicks landing on each position
+    pub fn click_visits(&self) -> Vec<u64> {
+        self.partial_visits
+            .iter()
+            .map(|visits| visits + self.full_revolutions)
+            .collect()
+    }
+
+    fn count_clicks(&mut self, mut pos: u64, dir: Direction, distance: u64) {
+        self.full_revolutions += distance / self.dial.size;
+
+        for _ in 0..distance % self.dial.size {
+            pos = self.dial.turn(pos, dir, 1);
+            self.partial_visits[pos as usize] += 1;
+        }
+    }
+
+    fn track_stretches(&mut self, pos: u64, dir: Direction, distance: u64) {
+        let size = self.dial.size;
+        let mut first_hits: Vec<u64> = self
+            .dial
+            .targets
+            .iter()
+            .map(|target| self.dial.first_hit(pos, dir, *target))
+            .collect();
+        first_hits.sort();
+
+        let Some(&first) = first_hits.first() else {
+            return;
+        };
+
+        if distance < first {
+            if let Some(clicks) = self.clicks_since_hit.as_mut() {
+                *clicks += distance;
+            }
+            return;
+        }
+
+        let mut stretches = Vec::new();
+
+        if let Some(clicks) = self.clicks_since_hit {
+            stretches.push(clicks + first);
+        }
+
+        // After the first revolution the hits repeat the same pattern, so two revolutions show every stretch
+        let horizon = distance.min(first.saturating_add(size.saturating_mul(2)));
+        let mut offsets = Vec::new();
+
+        for first_hit in &first_hits {
+            let mut offset = *first_hit;
+
+            while offset <= horizon {
+                offsets.push(offset);
+
+                match offset.checked_add(size) {
+                    Some(next) => offset = next,
+                    None => break,
+                }
+            }
+        }
+
+        offsets.sort();
+        stretches.extend(offsets.windows(2).map(|pair| pair[1] - pair[0]));
+
+        if let Some(longest) = stretches.into_iter().max() {
+            self.longest_stretch = self.longest_stretch.max(Some(longest));
+        }
+
+        let last_hit = first_hits
+            .iter()
+            .filter(|first_hit| **first_hit <= distance)
+            .map(|first_hit| first_hit + (distance - first_hit) / size * size)
+            .max()
+            .unwrap_or(first);
+
+        self.clicks_since_hit = Some(distance - last_hit);
+    }
+}
+
+impl fmt::Display for DialStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "left wraps: {}", self.left_wraps)?;
+        writeln!(f, "right wraps: {}", self.right_wraps)?;
+
+        match self.longest_rotation {
+            Some(rotation) => writeln!(f, "longest rotation: {}", rotation)?,
+            None => writeln!(f, "longest rotation: -")?,
+        }
+
+        match self.longest_stretch {
+            Some(clicks) => writeln!(f, "longest stretch between hits: {} clicks", clicks)?,
+            None => writeln!(f, "longest stretch between hits: -")?,
+        }
+
+        writeln!(
+            f,
+            "{:>8} | {:>12} | {:>13}",
+            "position", "clicks", "rotation ends"
+        )?;
+
+        for (pos, clicks) in self.click_visits().iter().enumerate() {
+            if *clicks > 0 || self.end_visits[pos] > 0 {
+                writeln!(
+                    f,
+                    "{:>8} | {:>12} | {:>13}",
+                    pos, clicks, self.end_visits[pos]
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generator::Rng;
+    use crate::rotation::RotationReader;
+    use crate::{CountMode, simulate};
+
+    fn stats(input: &str, dial: &Dial, mode: CountMode) -> DialStats {
+        let mut stats = DialStats::new(dial);
+        let rotations = RotationReader::new(input.as_bytes()).collect::<Vec<_>>();
+        simulate(rotations, dial, mode, |event| stats.record(&event));
+        stats
+    }
+
+    // Steps through every click to find the longest stretch between hits
+    fn longest_stretch_by_click(input: &str, dial: &Dial) -> Option<u64> {
+        let mut pos = dial.start;
+        let mut since_hit = None;
+        let mut longest = None;
+
+        for rotation in RotationReader::new(input.as_bytes()).collect::<Vec<_>>() {
+            for _ in 0..rotation.distance {
+                pos = dial.turn(pos, rotation.direction, 1);
+                since_hit = since_hit.map(|clicks| clicks + 1);
+
+                if dial.targets.contains(&pos) {
+                    longest = longest.max(since_hit);
+                    since_hit = Some(0);
+                }
+            }
+        }
+
+        longest
+    }
+
+    #[test]
+    fn example_input_stats() {
+        let input = std::fs::read_to_string("data/example_input").unwrap();
+        let dial = Dial::default();
+
+        for mode in [CountMode::Click, CountMode::ClosedForm] {
+            let stats = stats(&input, &dial, mode);
+            let clicks = stats.click_visits();
+
+            assert_eq!(stats.left_wraps, 4);
+            assert_eq!(stats.right_wraps, 2);
+            assert_eq!(stats.longest_rotation.unwrap().to_string(), "L99");
+            assert_eq!(clicks[0], 6);
+            assert_eq!(clicks.iter().sum::<u64>(), 462);
+            assert_eq!(stats.end_visits[0], 3);
+            assert_eq!(stats.end_visits[32], 1);
+            assert_eq!(stats.end_visits.iter().sum::<u64>(), 10);
+            assert_eq!(
+                stats.longest_stretch,
+                longest_stretch_by_click(&input, &dial)
+            );
+        }
+    }
+
+    #[test]
+    fn full_revolutions_visit_every_position() {
+        let stats = stats("R250\n", &Dial::default(), CountMode::ClosedForm);
+        let clicks = stats.click_visits();
+
+        assert_eq!(clicks[50], 2);
+        assert_eq!(clicks[51], 3);
+        assert_eq!(clicks[0], 3);
+        assert_eq!(clicks[49], 2);
+        assert_eq!(stats.longest_stretch, Some(100));
+    }
+
+    #[test]
+    fn stretches_match_click_by_click() {
+        let dial = Dial::new(12, 5, &[0, 3, 4]);
+        let input = "R2\nL30\nR1\nR1\nL1\nR13\nL7\nR40\n";
+
+        let stats = stats(input, &dial, CountMode::ClosedForm);
+
+        assert_eq!(
+            stats.longest_stretch,
+            longest_stretch_by_click(input, &dial)
+        );
+    }
+
+    #[test]
+    fn random_stretches_match_click_by_click() {
+        let mut rng = Rng::new(11);
+        let dial = Dial::new(9, 4, &[1, 2, 6]);
+
+        for _ in 0..50 {
+            let input: String = (0..6)
+                .map(|_| {
+                    let dir = if rng.below(2) == 0 { 'L' } else { 'R' };
+                    format!("{}{}\n", dir, rng.below(40))
+                })
+                .collect();
+
+            let stats = stats(&input, &dial, CountMode::ClosedForm);
+
+            assert_eq!(
+                stats.longest_stretch,
+                longest_stretch_by_click(&input, &dial)
+            );
+        }
+    }
+
+    #[test]
+    fn no_hits_no_stretch() {
+        let stats = stats("R10\nL3\n", &Dial::default(), CountMode::ClosedForm);
+
+        assert_eq!(stats.longest_stretch, None);
+        assert_eq!(stats.left_wraps + stats.right_wraps, 0);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::ranges::{IdRange, normalize};
+use crate::{RepeatRule, blocks_with_period, check_radix};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// The invalid IDs in a set of ranges under the rule, smallest first and each only once, even if
+// ranges overlap. IDs are made from their repeated blocks one at a time instead of checking every
+// ID, so nothing is built up front and take, skip_while and count only do the work they need.
+pub struct InvalidIds {
+    ranges: Vec<IdRange>,
+    radix: u32,
+    rule: RepeatRule,
+    // Range and digit length the heap holds the IDs for
+    range: usize,
+    digits: u32,
+    // Next ID for each period, with its block, multiplier and last block
+    heap: BinaryHeap<Reverse<(u128, u128, u128, u128)>>,
+    // Last ID given out, an ID with more than one period is in the heap more than once
+    last: Option<u128>,
+}
+
+impl InvalidIds {
+    pub fn new(ranges: &[IdRange], radix: u32, rule: RepeatRule) -> InvalidIds {
+        check_radix(radix);
+
+        InvalidIds {
+            ranges: normalize(ranges).ranges,
+            radix,
+            rule,
+            range: 0,
+            // Nothing has fewer than two digits, so start there
+            digits: 1,
+            heap: BinaryHeap::new(),
+            last: None,
+        }
+    }
+
+    // Moves on to the next digit length, or the next range once past the longest IDs in this one.
+    // Fills the heap with the first ID for every period. False once there are no ranges left.
+    fn advance(&mut self) -> bool {
+        let base = self.radix as u128;
+
+        let Some(&IdRange { lower, upper }) = self.ranges.get(self.range) else {
+            return false;
+        };
+
+        self.digits += 1;
+
+        if self.digits > upper.max(1).ilog(base) + 1 {
+            self.range += 1;
+            let Some(next) = self.ranges.get(self.range) else {
+                return false;
+            };
+            self.digits = (next.lower.max(1).ilog(base) + 1).max(2);
+        } else {
+            self.digits = self.digits.max(lower.max(1).ilog(base) + 1);
+        }
+
+        self.fill();
+        true
+    }
+
+    fn fill(&mut self) {
+        let IdRange { lower, upper } = self.ranges[self.range];
+        let digits = self.digits;
+
+        let periods: Vec<u32> = match self.rule {
+            RepeatRule::Twice if digits.is_multiple_of(2) => vec![digits / 2],
+            RepeatRule::Twice => vec![],
+            RepeatRule::AtLeastTwice => (1..digits).filter(|p| digits.is_multiple_of(*p)).collect(),
+        };
+
+        for period in periods {
+            if let Some((multiplier, first_block, last_block)) =
+                blocks_with_period(lower, upper, self.radix, digits, period)
+            {
+                self.heap.push(Reverse((
+                    first_block * multiplier,
+                    first_block,
+                    multiplier,
+                    last_block,
+                )));
+            }
+        }
+    }
+}
+
+impl Iterator for InvalidIds {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        loop {
+            let Some(Reverse((id, block, multiplier, last_block))) = self.heap.pop() else {
+                if !self.advance() {
+                    return None;
+                }
+                continue;
+            };
+
+            // The next block's ID is still in the range, so it can't overflow
+            if block < last_block {
+                self.heap.push(Reverse((
+                    id + multiplier,
+                    block + 1,
+                    multiplier,
+                    last_block,
+                )));
+            }
+
+            if self.last != Some(id) {
+                self.last = Some(id);
+                return Some(id);
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for InvalidIds {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{is_invalid_id_part_1, is_invalid_id_part_2};
+
+    fn range(lower: u128, upper: u128) -> IdRange {
+        IdRange { lower, upper }
+    }
+
+    #[test]
+    fn sample_input() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let ranges = crate::ranges::RangeParser::new(&input, 10)
+            .parse(crate::ranges::OnError::Abort)
+            .unwrap()
+            .ranges;
+
+        let part_1: Vec<u128> = InvalidIds::new(&ranges, 10, RepeatRule::Twice).collect();
+        assert_eq!(part_1.len(), 8);
+        assert_eq!(part_1.iter().sum::<u128>(), 1227775554);
+
+        let part_2 = InvalidIds::new(&ranges, 10, RepeatRule::AtLeastTwice);
+        assert_eq!(part_2.sum::<u128>(), 4174379265);
+    }
+
+    #[test]
+    fn matches_checking_every_id() {
+        let ranges = [
+            range(0, 3000),
+            range(95, 115),
+            range(9990, 10101),
+            range(121000, 125000),
+        ];
+
+        for radix in [2, 10, 16] {
+            let part_1: Vec<u128> = InvalidIds::new(&ranges, radix, RepeatRule::Twice).collect();
+            let part_2: Vec<u128> =
+                InvalidIds::new(&ranges, radix, RepeatRule::AtLeastTwice).collect();
+
+            // 0-3000 already has 95-115 in it
+            let every_id = || {
+                [(0, 3000), (9990, 10101), (121000, 125000)]
+                    .into_iter()
+                    .flat_map(|(lower, upper)| lower..=upper)
+            };
+
+            let expected_1: Vec<u128> = every_id()
+                .filter(|id| is_invalid_id_part_1(*id, radix, false))
+                .collect();
+            let expected_2: Vec<u128> = every_id()
+                .filter(|id| is_invalid_id_part_2(*id, radix, false))
+                .collect();
+
+            assert_eq!(part_1, expected_1);
+            assert_eq!(part_2, expected_2);
+        }
+    }
+
+    #[test]
+    fn sorted_without_duplicates() {
+        // Out of order and overlapping, and 111111 has periods 1, 2 and 3
+        let ranges = [
+            range(111000, 112000),
+            range(10, 100),
+            range(50, 120),
+            range(111111, 111111),
+        ];
+        let ids: Vec<u128> = InvalidIds::new(&ranges, 10, RepeatRule::AtLeastTwice).collect();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(&ids[..3], [11, 22, 33]);
+        assert_eq!(ids.iter().filter(|id| **id == 111111).count(), 1);
+        assert!(InvalidIds::new(&[], 10, RepeatRule::Twice).next().is_none());
+        assert!(
+            InvalidIds::new(&[range(0, 9)], 10, RepeatRule::Twice)
+                .next()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn lazy_over_huge_ranges() {
+        // Far too many IDs to list, only the ones asked for are made
+        let ranges = [range(0, u128::MAX)];
+
+        let first: Vec<u128> = InvalidIds::new(&ranges, 10, RepeatRule::AtLeastTwice)
+            .take(12)
+            .collect();
+        assert_eq!(first, [11, 22, 33, 44, 55, 66, 77, 88, 99, 111, 222, 333]);
+
+        let mut after =
+            InvalidIds::new(&ranges, 10, RepeatRule::Twice).skip_while(|id| *id < 10_u128.pow(10));
+        assert_eq!(after.next(), Some(100000100000));
+
+        // Every 4 digit part 1 ID is a two digit block twice
+        let count = InvalidIds::new(&[range(1000, 9999)], 10, RepeatRule::Twice).count();
+        assert_eq!(count, 90);
+
+        // 39 digits can't be anything twice
+        let ids = InvalidIds::new(&[range(10_u128.pow(38), u128::MAX)], 10, RepeatRule::Twice);
+        assert_eq!(ids.count(), 0);
+    }
+}
@@ -54,10 +54,48 @@
 //
 // What do you get if you add up all of the invalid IDs using these new rules?
 
-use std::num::ParseIntError;
+mod ids;
+mod parallel;
+mod patterns;
+mod ranges;
+mod repeats;
+mod report;
+mod validator;
+
+use ids::InvalidIds;
+use parallel::sum_invalid_ids_parallel;
+use ranges::{IdRange, OnError, RangeParser, normalize};
+use repeats::RepeatCounts;
+use report::Report;
+use std::thread;
+use validator::{IdValidator, RepeatedDigits, parse_rule};
+
+// Every validator and listing takes a radix, which has to be one from_str_radix can read
+fn check_radix(radix: u32) {
+    assert!((2..=36).contains(&radix), "radix must be between 2 and 36");
+}
+
+// The digits of id written in the given radix (2 to 36), lowercase and without leading zeroes
+fn to_digits(id: u128, radix: u32) -> String {
+    check_radix(radix);
+
+    if id == 0 {
+        return "0".to_string();
+    }
+
+    let mut digits = Vec::new();
+    let mut rest = id;
 
-fn is_invalid_id_part_1(id: u64, verbose: bool) -> bool {
-    let id_str = id.to_string();
+    while rest > 0 {
+        digits.push(char::from_digit((rest % radix as u128) as u32, radix).unwrap());
+        rest /= radix as u128;
+    }
+
+    digits.iter().rev().collect()
+}
+
+fn is_invalid_id_part_1(id: u128, radix: u32, verbose: bool) -> bool {
+    let id_str = to_digits(id, radix);
     let digits = id_str.len();
 
     if !digits.is_multiple_of(2) {
@@ -70,13 +108,13 @@ fn is_invalid_id_part_1(id: u64, verbose: bool) -> bool {
     let is_invalid = left == right;
 
     if verbose && is_invalid {
-        println!("Invalid ID: {}", id);
+        println!("Invalid ID: {}", id_str);
     }
     is_invalid
 }
 
-fn is_invalid_id_part_2(id: u64, verbose: bool) -> bool {
-    let id_str = id.to_string();
+fn is_invalid_id_part_2(id: u128, radix: u32, verbose: bool) -> bool {
+    let id_str = to_digits(id, radix);
     let digits = id_str.len();
 
     for n in 2..=digits {
@@ -124,94 +162,699 @@ fn is_invalid_id_part_2(id: u64, verbose: bool) -> bool {
     false
 }
 
-fn find_invalid_ids(lower: u64, upper: u64, validator: IDValidator, verbose: bool) -> u64 {
-    let mut sum = 0;
-    for n in lower..=upper {
-        if validator(n, verbose) {
-            sum += n;
+// None if the sum doesn't fit in a u128
+fn find_invalid_ids<V: IdValidator>(lower: u128, upper: u128, validator: &V) -> Option<u128> {
+    validator.sum_invalid(lower, upper)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RepeatRule {
+    // Part 1, a block of digits repeated exactly twice
+    Twice,
+    // Part 2, a block of digits repeated two or more times
+    AtLeastTwice,
+}
+
+fn mobius(n: u32) -> i32 {
+    let mut n = n;
+    let mut result = 1;
+    let mut p = 2;
+
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            n /= p;
+            if n.is_multiple_of(p) {
+                return 0;
+            }
+            result = -result;
         }
+        p += 1;
     }
-    sum
+
+    if n > 1 { -result } else { result }
 }
 
-fn parse_inputs(case: &str) -> Result<(u64, u64), ParseIntError> {
-    let splitted: Vec<&str> = case.split('-').collect();
-    let low = splitted[0].trim().parse()?;
-    let high = splitted[1].trim().parse()?;
+// A number that may not fit in a u128, as its high and low 128 bits. Inclusion-exclusion terms can
+// be too large even when what's left after taking the other terms off fits.
+type Wide = (u128, u128);
+
+fn wide_mul(a: u128, b: u128) -> Wide {
+    let half = |x: u128| (x >> 64, x & u64::MAX as u128);
+    let ((a_high, a_low), (b_high, b_low)) = (half(a), half(b));
+
+    let (middle, middle_carry) = (a_high * b_low).overflowing_add(a_low * b_high);
+    let (low, low_carry) = (a_low * b_low).overflowing_add(middle << 64);
+    let high =
+        a_high * b_high + (middle >> 64) + ((middle_carry as u128) << 64) + low_carry as u128;
 
-    Ok((low, high))
+    (high, low)
 }
 
-fn parse_ranges(input: String, validator: IDValidator, verbose: bool) -> u64 {
-    let mut sum = 0;
-    for case in input.split(',') {
-        if case.is_empty() {
-            continue;
-        }
+fn wide_add(a: Wide, b: Wide) -> Option<Wide> {
+    let (low, carry) = a.1.overflowing_add(b.1);
+    Some((a.0.checked_add(b.0)?.checked_add(carry as u128)?, low))
+}
+
+// None if b is larger than a
+fn wide_sub(a: Wide, b: Wide) -> Option<Wide> {
+    let (low, borrow) = a.1.overflowing_sub(b.1);
+    Some((a.0.checked_sub(b.0)?.checked_sub(borrow as u128)?, low))
+}
+
+fn narrow((high, low): Wide) -> Option<u128> {
+    (high == 0).then_some(low)
+}
 
-        let (low, high) = match parse_inputs(case) {
-            Ok((low, high)) => (low, high),
-            Err(e) => panic!("error parsing int, {}", e),
+// Sum of first..=last, which always fits in a Wide
+fn wide_series_sum(first: u128, last: u128) -> Wide {
+    // 0 adds nothing, and leaving it out keeps the count in a u128
+    if first == 0 {
+        return if last == 0 {
+            (0, 0)
+        } else {
+            wide_series_sum(1, last)
         };
+    }
+
+    // first * count + (0 + 1 + ... + count - 1), halving whichever factor of the last part is even
+    let count = last - first + 1;
+    let (even, odd) = if count.is_multiple_of(2) {
+        (count, count - 1)
+    } else {
+        (count - 1, count)
+    };
 
-        let res = find_invalid_ids(low, high, validator, verbose);
+    wide_add(wide_mul(first, count), wide_mul(even / 2, odd)).unwrap()
+}
+
+// Sum of first..=last, None if it doesn't fit in a u128
+fn series_sum(first: u128, last: u128) -> Option<u128> {
+    narrow(wide_series_sum(first, last))
+}
 
-        sum += res;
+// The IDs in lower..=upper with `digits` digits in the radix that are a block of `period` digits
+// repeated. Such an ID is block * 1..01..01, so they are given as that multiplier and the first and
+// last block. None if there aren't any, including when they would be too large for a u128.
+fn blocks_with_period(
+    lower: u128,
+    upper: u128,
+    radix: u32,
+    digits: u32,
+    period: u32,
+) -> Option<(u128, u128, u128)> {
+    let base = radix as u128;
+    let largest = |digits| {
+        base.checked_pow(digits)
+            .map_or(u128::MAX, |power| power - 1)
+    };
+
+    let multiplier = (0..digits / period).try_fold(0_u128, |multiplier, i| {
+        multiplier.checked_add(base.checked_pow(period * i)?)
+    })?;
+
+    let low = lower.max(base.checked_pow(digits - 1)?);
+    let high = upper.min(largest(digits));
+
+    if low > high {
+        return None;
     }
-    sum
+
+    // No leading zeroes in the block either
+    let first_block = low.div_ceil(multiplier).max(base.pow(period - 1));
+    let last_block = (high / multiplier).min(largest(period));
+
+    if first_block > last_block {
+        return None;
+    }
+
+    Some((multiplier, first_block, last_block))
 }
 
-type IDValidator = fn(u64, bool) -> bool;
+// Sum of the IDs in lower..=upper with `digits` digits in the radix that are a block of `period`
+// digits repeated. The blocks in range form an arithmetic series.
+fn wide_sum_with_period(lower: u128, upper: u128, radix: u32, digits: u32, period: u32) -> Wide {
+    match blocks_with_period(lower, upper, radix, digits, period) {
+        Some((multiplier, first_block, last_block)) => {
+            let (high, low) = wide_series_sum(first_block, last_block);
+            let (carry, low) = wide_mul(multiplier, low);
+
+            // Every ID is at most upper, so there are too few of them for this to overflow
+            (multiplier * high + carry, low)
+        }
+        None => (0, 0),
+    }
+}
+
+// Same as wide_sum_with_period, None if the sum doesn't fit in a u128
+fn sum_with_period(lower: u128, upper: u128, radix: u32, digits: u32, period: u32) -> Option<u128> {
+    narrow(wide_sum_with_period(lower, upper, radix, digits, period))
+}
+
+// A block of period digits also matches every block size it divides, so this sums the IDs whose
+// smallest block is `period` digits, using inclusion-exclusion over the divisors. The terms are
+// summed without a limit, so None only means the result itself doesn't fit in a u128.
+fn sum_by_smallest_period(
+    lower: u128,
+    upper: u128,
+    radix: u32,
+    digits: u32,
+    period: u32,
+) -> Option<u128> {
+    let mut added: Wide = (0, 0);
+    let mut removed: Wide = (0, 0);
+
+    for sub_period in (1..=period).filter(|p| period.is_multiple_of(*p)) {
+        let sum = wide_sum_with_period(lower, upper, radix, digits, sub_period);
+
+        match mobius(period / sub_period) {
+            1 => added = wide_add(added, sum)?,
+            -1 => removed = wide_add(removed, sum)?,
+            _ => {}
+        }
+    }
+
+    narrow(wide_sub(added, removed)?)
+}
+
+// Same as summing is_invalid_id_part_1 or is_invalid_id_part_2 over the range, but only looks
+// at each digit length and period instead of every ID. None if the sum doesn't fit in a u128.
+fn sum_repeated_ids(lower: u128, upper: u128, radix: u32, rule: RepeatRule) -> Option<u128> {
+    check_radix(radix);
+
+    let min_digits = lower.max(1).ilog(radix as u128) + 1;
+    let max_digits = upper.max(1).ilog(radix as u128) + 1;
+
+    let mut sum: u128 = 0;
+
+    for digits in min_digits..=max_digits {
+        match rule {
+            RepeatRule::Twice => {
+                if digits.is_multiple_of(2) {
+                    let ids = sum_with_period(lower, upper, radix, digits, digits / 2)?;
+                    sum = sum.checked_add(ids)?;
+                }
+            }
+            RepeatRule::AtLeastTwice => {
+                for period in (1..digits).filter(|p| digits.is_multiple_of(*p)) {
+                    let ids = sum_by_smallest_period(lower, upper, radix, digits, period)?;
+                    sum = sum.checked_add(ids)?;
+                }
+            }
+        }
+    }
+
+    Some(sum)
+}
+
+fn sum_invalid_ids<V: IdValidator>(ranges: &[IdRange], validator: &V) -> Option<u128> {
+    let mut sum: u128 = 0;
+    for range in ranges {
+        let res = find_invalid_ids(range.lower, range.upper, validator)?;
+
+        sum = sum.checked_add(res)?;
+    }
+    Some(sum)
+}
+
+// Sums too large for a u128 are reported rather than wrapped around
+fn format_sum(sum: Option<u128>) -> String {
+    match sum {
+        Some(sum) => sum.to_string(),
+        None => "too large for u128".to_string(),
+    }
+}
 
 fn main() {
     let input = std::fs::read_to_string("data/input").unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // IDs and ranges in another base, e.g. --radix=16
+    let radix = match args.iter().find_map(|arg| arg.strip_prefix("--radix=")) {
+        Some(radix) => match radix.parse() {
+            Ok(radix @ 2..=36) => radix,
+            _ => {
+                println!("radix must be between 2 and 36, got '{}'", radix);
+                return;
+            }
+        },
+        None => 10,
+    };
+
+    // Spread the ranges over threads, --threads for one per core or --threads=N
+    let threads = match args.iter().find_map(|arg| arg.strip_prefix("--threads=")) {
+        Some(threads) => match threads.parse() {
+            Ok(threads) if threads > 0 => threads,
+            _ => {
+                println!("thread count must be a positive number, got '{}'", threads);
+                return;
+            }
+        },
+        None if args.iter().any(|arg| arg == "--threads") => {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        }
+        None => 1,
+    };
 
-    let validator_part_1: IDValidator = is_invalid_id_part_1;
-    let validator_part_2: IDValidator = is_invalid_id_part_2;
+    let mut parser = RangeParser::new(&input, radix);
+    if args.iter().any(|arg| arg == "--allow-reversed") {
+        parser = parser.allow_reversed();
+    }
+    if args.iter().any(|arg| arg == "--allow-overlapping") {
+        parser = parser.allow_overlapping();
+    }
 
-    let sum_part_1 = parse_ranges(input.clone(), validator_part_1, false);
-    println!("sum part 1: {}", sum_part_1);
+    let on_error = if args.iter().any(|arg| arg == "--skip-errors") {
+        OnError::Skip
+    } else {
+        OnError::Abort
+    };
 
-    let sum_part_2 = parse_ranges(input.clone(), validator_part_2, false);
-    println!("sum part 2: {}", sum_part_2);
+    let ranges = match parser.parse(on_error) {
+        Ok(parsed) => {
+            for err in parsed.skipped {
+                println!("skipped range at {}", err);
+            }
+            parsed.ranges
+        }
+        Err(err) => {
+            println!("error reading ranges: {}", err);
+            return;
+        }
+    };
+
+    // Overlapping ranges only get through with --allow-overlapping, their IDs are still counted once
+    let normalized = normalize(&ranges);
+    for (first, second) in &normalized.overlaps {
+        println!(
+            "ranges {} and {} overlap",
+            ranges[*first].in_radix(radix),
+            ranges[*second].in_radix(radix)
+        );
+    }
+
+    // Every invalid ID per range, --report for a table or --report=json. The ranges are shown as they
+    // are in the input, touching or overlapping ones aren't merged
+    if let Some(arg) = args.iter().find(|arg| arg.starts_with("--report")) {
+        let report = Report::new(&ranges, radix);
+
+        match arg.as_str() {
+            "--report" => print!("{}", report),
+            "--report=json" => println!("{}", report.to_json()),
+            _ => println!("unknown report format '{}'", arg),
+        }
+        return;
+    }
+
+    let ranges = normalized.ranges;
+
+    // The part 2 invalid IDs themselves, --list for all of them or --list=N for the first N
+    if let Some(arg) = args.iter().find(|arg| arg.starts_with("--list")) {
+        let limit = match arg.strip_prefix("--list=") {
+            None => usize::MAX,
+            Some(limit) => match limit.parse() {
+                Ok(limit) => limit,
+                Err(_) => {
+                    println!("list length must be a number, got '{}'", limit);
+                    return;
+                }
+            },
+        };
+
+        for id in InvalidIds::new(&ranges, radix, RepeatRule::AtLeastTwice).take(limit) {
+            println!("{}", to_digits(id, radix));
+        }
+        return;
+    }
+
+    // Only IDs repeated some number of times, e.g. --repeats=3,5
+    if let Some(counts) = args.iter().find_map(|arg| arg.strip_prefix("--repeats=")) {
+        let counts: Result<Vec<u32>, _> = counts
+            .split(',')
+            .map(|count| count.trim().parse())
+            .collect();
+
+        match counts {
+            Ok(counts) if !counts.is_empty() && !counts.contains(&0) => {
+                let validator = RepeatCounts::new(&counts, radix);
+                let names: Vec<String> = counts.iter().map(|count| count.to_string()).collect();
+
+                println!(
+                    "sum repeated {} times: {}",
+                    names.join(" or "),
+                    format_sum(sum_invalid_ids_parallel(&ranges, &validator, threads))
+                );
+            }
+            _ => println!("repeat counts must be positive numbers separated by commas"),
+        }
+        return;
+    }
+
+    // Combinations of rules, e.g. --rule="part2 & !part1"
+    if let Some(rule) = args.iter().find_map(|arg| arg.strip_prefix("--rule=")) {
+        match parse_rule(rule, radix) {
+            Ok(validator) => println!(
+                "sum {}: {}",
+                rule,
+                format_sum(sum_invalid_ids_parallel(&ranges, &validator, threads))
+            ),
+            Err(err) => println!("error parsing rule: {}", err),
+        }
+        return;
+    }
+
+    // Checking every single ID is slow, but kept around to compare against
+    if args.iter().any(|arg| arg == "--brute-force") {
+        let validator_part_1 = |id| is_invalid_id_part_1(id, radix, false);
+        let validator_part_2 = |id| is_invalid_id_part_2(id, radix, false);
+
+        let sum_part_1 = sum_invalid_ids_parallel(&ranges, &validator_part_1, threads);
+        println!("sum part 1: {}", format_sum(sum_part_1));
+
+        let sum_part_2 = sum_invalid_ids_parallel(&ranges, &validator_part_2, threads);
+        println!("sum part 2: {}", format_sum(sum_part_2));
+        return;
+    }
+
+    let sum_part_1 = sum_invalid_ids_parallel(
+        &ranges,
+        &RepeatedDigits::new(RepeatRule::Twice, radix),
+        threads,
+    );
+    println!("sum part 1: {}", format_sum(sum_part_1));
+
+    let sum_part_2 = sum_invalid_ids_parallel(
+        &ranges,
+        &RepeatedDigits::new(RepeatRule::AtLeastTwice, radix),
+        threads,
+    );
+    println!("sum part 2: {}", format_sum(sum_part_2));
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    // Read the way main reads them, overlapping ranges folded together
+    fn read_ranges(input: &str, radix: u32) -> Vec<IdRange> {
+        let parsed = RangeParser::new(input, radix)
+            .parse(OnError::Abort)
+            .unwrap();
+
+        normalize(&parsed.ranges).ranges
+    }
+
     #[test]
     fn is_invalid_id_test() {
-        assert!(is_invalid_id_part_1(11, false));
-        assert!(is_invalid_id_part_1(22, false));
-        assert!(is_invalid_id_part_1(1188511885, false));
+        assert!(is_invalid_id_part_1(11, 10, false));
+        assert!(is_invalid_id_part_1(22, 10, false));
+        assert!(is_invalid_id_part_1(1188511885, 10, false));
 
-        assert!(!is_invalid_id_part_1(1011, false));
-        assert!(!is_invalid_id_part_1(1188611885, false));
+        assert!(!is_invalid_id_part_1(1011, 10, false));
+        assert!(!is_invalid_id_part_1(1188611885, 10, false));
     }
 
     #[test]
     fn sample_input_part_1() {
         let input = std::fs::read_to_string("data/sample_input").unwrap();
-        let validator: IDValidator = is_invalid_id_part_1;
-        let sum = parse_ranges(input, validator, true);
-        assert_eq!(sum, 1227775554);
+        let validator = |id| is_invalid_id_part_1(id, 10, true);
+        let sum = sum_invalid_ids(&read_ranges(&input, 10), &validator);
+        assert_eq!(sum, Some(1227775554));
     }
 
     #[test]
     fn part_2_invalid_check() {
-        assert_eq!(is_invalid_id_part_2(11, true), true);
-        assert_eq!(is_invalid_id_part_2(22, true), true);
-        assert_eq!(is_invalid_id_part_2(13, true), false);
-        assert_eq!(is_invalid_id_part_2(1188511885, true), true);
-        assert_eq!(is_invalid_id_part_2(1111111, true), true);
+        assert!(is_invalid_id_part_2(11, 10, true));
+        assert!(is_invalid_id_part_2(22, 10, true));
+        assert!(!is_invalid_id_part_2(13, 10, true));
+        assert!(is_invalid_id_part_2(1188511885, 10, true));
+        assert!(is_invalid_id_part_2(1111111, 10, true));
     }
 
     #[test]
     fn sample_input_part_2() {
         let input = std::fs::read_to_string("data/sample_input").unwrap();
-        let validator: IDValidator = is_invalid_id_part_2;
-        let sum = parse_ranges(input, validator, true);
-        assert_eq!(sum, 4174379265);
+        let validator = |id| is_invalid_id_part_2(id, 10, true);
+        let sum = sum_invalid_ids(&read_ranges(&input, 10), &validator);
+        assert_eq!(sum, Some(4174379265));
+    }
+
+    #[test]
+    fn sample_input_closed_form() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let ranges = read_ranges(&input, 10);
+
+        let sum_part_1 = sum_invalid_ids(&ranges, &RepeatRule::Twice);
+        assert_eq!(sum_part_1, Some(1227775554));
+
+        let sum_part_2 = sum_invalid_ids(&ranges, &RepeatRule::AtLeastTwice);
+        assert_eq!(sum_part_2, Some(4174379265));
+    }
+
+    #[test]
+    fn closed_form_matches_validators() {
+        for lower in (1..1300).step_by(13) {
+            for width in [0, 1, 10, 95, 1000] {
+                let upper = lower + width;
+
+                assert_eq!(
+                    sum_repeated_ids(lower, upper, 10, RepeatRule::Twice),
+                    find_invalid_ids(lower, upper, &|id| is_invalid_id_part_1(id, 10, false))
+                );
+                assert_eq!(
+                    sum_repeated_ids(lower, upper, 10, RepeatRule::AtLeastTwice),
+                    find_invalid_ids(lower, upper, &|id| is_invalid_id_part_2(id, 10, false))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn closed_form_matches_validators_many_digits() {
+        // Covers 6 digits (periods 1, 2 and 3) and the jump from 7 to 8 digits
+        for (lower, upper) in [(121000, 125000), (9999000, 10010101), (111110, 111112)] {
+            assert_eq!(
+                sum_repeated_ids(lower, upper, 10, RepeatRule::Twice),
+                find_invalid_ids(lower, upper, &|id| is_invalid_id_part_1(id, 10, false))
+            );
+            assert_eq!(
+                sum_repeated_ids(lower, upper, 10, RepeatRule::AtLeastTwice),
+                find_invalid_ids(lower, upper, &|id| is_invalid_id_part_2(id, 10, false))
+            );
+        }
+    }
+
+    #[test]
+    fn digits_in_other_radixes() {
+        assert_eq!(to_digits(255, 16), "ff");
+        assert_eq!(to_digits(5, 2), "101");
+        assert_eq!(to_digits(0, 36), "0");
+        assert_eq!(to_digits(u64::MAX as u128, 36), "3w5e11264sgsf");
+        assert_eq!(to_digits(u128::MAX, 16), "f".repeat(32));
+
+        assert!(is_invalid_id_part_1(0xabab, 16, false));
+        assert!(!is_invalid_id_part_1(0xabab, 10, false));
+        assert!(is_invalid_id_part_2(0b111, 2, false));
+        assert!(is_invalid_id_part_2(36 * 36 + 36 + 1, 36, false));
+
+        // 0x0101 is just 101 in hex, leading zeroes don't count as part of a block
+        assert!(!is_invalid_id_part_1(0x0101, 16, false));
+        assert!(!is_invalid_id_part_2(0b0101, 2, false));
+    }
+
+    #[test]
+    fn closed_form_matches_validators_in_other_radixes() {
+        for radix in [2, 3, 7, 16, 36] {
+            for lower in (0..5000).step_by(307) {
+                let upper = lower + 2000;
+
+                assert_eq!(
+                    sum_repeated_ids(lower, upper, radix, RepeatRule::Twice),
+                    find_invalid_ids(lower, upper, &|id| is_invalid_id_part_1(id, radix, false))
+                );
+                assert_eq!(
+                    sum_repeated_ids(lower, upper, radix, RepeatRule::AtLeastTwice),
+                    find_invalid_ids(lower, upper, &|id| is_invalid_id_part_2(id, radix, false))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bad_ranges_are_errors() {
+        let err = RangeParser::new("11-22,95", 10)
+            .parse(OnError::Abort)
+            .unwrap_err();
+        assert_eq!(err.offset, 6);
+    }
+
+    #[test]
+    fn overlapping_ids_counted_once() {
+        let input = "11-22,15-30,11-22,95-100";
+        let parsed = RangeParser::new(input, 10)
+            .allow_overlapping()
+            .parse(OnError::Abort)
+            .unwrap();
+        let ranges = normalize(&parsed.ranges).ranges;
+
+        assert_eq!(
+            sum_invalid_ids(&ranges, &RepeatRule::Twice),
+            Some(11 + 22 + 99)
+        );
+        assert_eq!(
+            sum_invalid_ids(&ranges, &|id| is_invalid_id_part_1(id, 10, false)),
+            Some(11 + 22 + 99)
+        );
+    }
+
+    #[test]
+    fn ranges_in_hex() {
+        // 0x11 is the only repeated ID between 0xa and 0x1f, 0xaa and 0xbb are repeated in the second range
+        let ranges = read_ranges("a-1F,a0-bb\n", 16);
+        let validator = |id| is_invalid_id_part_1(id, 16, false);
+
+        assert_eq!(
+            sum_invalid_ids(&ranges, &validator),
+            Some(0x11 + 0xaa + 0xbb)
+        );
+    }
+
+    // Builds every repeated-block ID in the range from its blocks, for ranges too large to check ID by ID
+    fn repeated_ids_from_blocks(lower: u128, upper: u128, only_twice: bool) -> u128 {
+        let mut ids = std::collections::BTreeSet::new();
+
+        for digits in 2..=38_u32 {
+            for period in (1..digits).filter(|p| digits % p == 0) {
+                if only_twice && period * 2 != digits {
+                    continue;
+                }
+
+                let multiplier = (10_u128.pow(digits) - 1) / (10_u128.pow(period) - 1);
+                let mut block = (lower / multiplier).max(10_u128.pow(period - 1));
+
+                while block < 10_u128.pow(period) && block * multiplier <= upper {
+                    if block * multiplier >= lower {
+                        ids.insert(block * multiplier);
+                    }
+                    block += 1;
+                }
+            }
+        }
+
+        ids.iter().sum()
+    }
+
+    #[test]
+    fn closed_form_huge_range() {
+        // 18 digit IDs, far too many to check one by one
+        let lower = 10_u128.pow(17);
+        let upper = 10_u128.pow(17) + 10_u128.pow(11);
+
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::Twice),
+            Some(repeated_ids_from_blocks(lower, upper, true))
+        );
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::AtLeastTwice),
+            Some(repeated_ids_from_blocks(lower, upper, false))
+        );
+
+        // 20 digits, the only repeated ID in here is 10 ten times, which is also 1010101010 twice
+        let lower = 10101010101010101000;
+        let upper = 10101010101010101100;
+
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::AtLeastTwice),
+            Some(10101010101010101010)
+        );
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::Twice),
+            Some(10101010101010101010)
+        );
+    }
+
+    #[test]
+    fn closed_form_beyond_u64() {
+        // 30 digit IDs, the ones repeated twice are blocks 10^14 up to 10^14 + 9
+        let lower = 10_u128.pow(29);
+        let upper = 10_u128.pow(29) + 10_u128.pow(16);
+
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::Twice),
+            Some(repeated_ids_from_blocks(lower, upper, true))
+        );
+        assert_eq!(
+            sum_repeated_ids(lower, upper, 10, RepeatRule::AtLeastTwice),
+            Some(repeated_ids_from_blocks(lower, upper, false))
+        );
+
+        let input = format!("{}-{}", lower, upper);
+        let ranges = RangeParser::new(&input, 10)
+            .parse(OnError::Abort)
+            .unwrap()
+            .ranges;
+        assert_eq!(
+            sum_invalid_ids(&ranges, &RepeatRule::Twice),
+            Some(repeated_ids_from_blocks(lower, upper, true))
+        );
+    }
+
+    #[test]
+    fn overflow_is_detected() {
+        // 39 ones is about a third of u128::MAX
+        let ones: u128 = "1".repeat(39).parse().unwrap();
+
+        assert_eq!(
+            sum_repeated_ids(ones, ones, 10, RepeatRule::AtLeastTwice),
+            Some(ones)
+        );
+        assert_eq!(
+            sum_repeated_ids(ones, ones * 3, 10, RepeatRule::AtLeastTwice),
+            None
+        );
+        assert_eq!(sum_repeated_ids(0, u128::MAX, 2, RepeatRule::Twice), None);
+
+        let everything = |_: u128| true;
+        assert_eq!(
+            find_invalid_ids(u128::MAX - 1, u128::MAX, &everything),
+            None
+        );
+        assert_eq!(
+            find_invalid_ids(u128::MAX - 1, u128::MAX, &RepeatRule::Twice.not()),
+            None
+        );
+        assert_eq!(format_sum(None), "too large for u128");
+    }
+
+    #[test]
+    fn no_overflow_when_the_sum_fits() {
+        // The four largest 38 digit IDs that are a 19 digit block twice add up to more than
+        // u128::MAX, but the last one is 38 nines, which has a smaller period and is taken off again
+        let multiplier = 10_u128.pow(19) + 1;
+        let lower = (10_u128.pow(19) - 4) * multiplier;
+        let upper = 10_u128.pow(38) - 1;
+        let expected = (1..=3)
+            .map(|i| (10_u128.pow(19) - 1 - i) * multiplier)
+            .sum::<u128>();
+
+        assert_eq!(sum_with_period(lower, upper, 10, 38, 19), None);
+        assert_eq!(
+            sum_by_smallest_period(lower, upper, 10, 38, 19),
+            Some(expected)
+        );
+        assert_eq!(
+            sum_repeated_ids(lower, upper - 1, 10, RepeatRule::AtLeastTwice),
+            Some(expected)
+        );
+
+        // 1010...10 in binary is the only 128 digit ID with smallest period 2, 1111...1 is taken off
+        assert_eq!(
+            sum_by_smallest_period(1 << 127, u128::MAX, 2, 128, 2),
+            Some(u128::MAX / 3 * 2)
+        );
+
+        assert_eq!(wide_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(wide_mul(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(wide_series_sum(0, u128::MAX), (u128::MAX / 2, 1 << 127));
+        assert_eq!(series_sum(1, 100), Some(5050));
     }
 }
@@ -0,0 +1,155 @@
+use crate::ranges::IdRange;
+use crate::validator::IdValidator;
+use crate::{find_invalid_ids, sum_invalid_ids};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Pieces per thread, so a thread that gets the slow pieces doesn't hold up the rest
+const PIECES_PER_THREAD: u128 = 4;
+
+// Cuts the ranges into pieces of about the same number of IDs, so a few huge ranges are shared
+// between threads as well
+fn split_ranges(ranges: &[IdRange], pieces: u128) -> Vec<IdRange> {
+    let total = ranges.iter().fold(0_u128, |total, range| {
+        total.saturating_add((range.upper - range.lower).saturating_add(1))
+    });
+    let piece_size = total.div_ceil(pieces.max(1)).max(1);
+
+    let mut split = Vec::new();
+
+    for range in ranges {
+        let mut lower = range.lower;
+
+        loop {
+            let upper = lower.saturating_add(piece_size - 1).min(range.upper);
+            split.push(IdRange { lower, upper });
+
+            if upper == range.upper {
+                break;
+            }
+            lower = upper + 1;
+        }
+    }
+
+    split
+}
+
+// Same as sum_invalid_ids, with the work spread over `threads` threads. Each piece's sum is kept in
+// its own slot and added up in order afterwards, so the result doesn't depend on which thread
+// finished first.
+pub fn sum_invalid_ids_parallel<V: IdValidator + Sync>(
+    ranges: &[IdRange],
+    validator: &V,
+    threads: usize,
+) -> Option<u128> {
+    if threads <= 1 {
+        return sum_invalid_ids(ranges, validator);
+    }
+
+    let pieces = split_ranges(ranges, threads as u128 * PIECES_PER_THREAD);
+    let next_piece = AtomicUsize::new(0);
+    let mut sums = vec![Some(0); pieces.len()];
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(pieces.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+
+                    loop {
+                        let i = next_piece.fetch_add(1, Ordering::Relaxed);
+                        let Some(piece) = pieces.get(i) else {
+                            break;
+                        };
+
+                        done.push((i, find_invalid_ids(piece.lower, piece.upper, validator)));
+                    }
+
+                    done
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (i, sum) in worker.join().expect("worker thread panicked") {
+                sums[i] = sum;
+            }
+        }
+    });
+
+    sums.into_iter()
+        .try_fold(0_u128, |total, sum| total.checked_add(sum?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ranges::{OnError, RangeParser};
+    use crate::validator::parse_rule;
+    use crate::{RepeatRule, is_invalid_id_part_2};
+
+    fn sample_ranges() -> Vec<IdRange> {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        RangeParser::new(&input, 10)
+            .parse(OnError::Abort)
+            .unwrap()
+            .ranges
+    }
+
+    #[test]
+    fn matches_serial() {
+        let ranges = sample_ranges();
+        let by_id = |id| is_invalid_id_part_2(id, 10, false);
+        let rule = parse_rule("part2 & !part1", 10).unwrap();
+
+        for threads in [0, 1, 2, 3, 8, 100] {
+            assert_eq!(
+                sum_invalid_ids_parallel(&ranges, &by_id, threads),
+                Some(4174379265)
+            );
+            assert_eq!(
+                sum_invalid_ids_parallel(&ranges, &RepeatRule::Twice, threads),
+                Some(1227775554)
+            );
+            assert_eq!(
+                sum_invalid_ids_parallel(&ranges, &rule, threads),
+                sum_invalid_ids(&ranges, &rule)
+            );
+        }
+    }
+
+    #[test]
+    fn pieces_cover_the_ranges() {
+        let ranges = [
+            IdRange {
+                lower: 1,
+                upper: 1000,
+            },
+            IdRange {
+                lower: 5000,
+                upper: 5000,
+            },
+            IdRange {
+                lower: u128::MAX - 10,
+                upper: u128::MAX,
+            },
+        ];
+
+        let pieces = split_ranges(&ranges, 7);
+        let count: u128 = pieces.iter().map(|p| p.upper - p.lower + 1).sum();
+
+        assert_eq!(count, 1000 + 1 + 11);
+        assert!(pieces.windows(2).all(|pair| pair[0].upper < pair[1].lower));
+        assert_eq!(pieces.last().unwrap().upper, u128::MAX);
+
+        let everything = [IdRange {
+            lower: 0,
+            upper: u128::MAX,
+        }];
+        let pieces = split_ranges(&everything, 3);
+        // The count of IDs saturates at u128::MAX, one short, so there can be a last piece of one ID
+        assert!(pieces.len() <= 4);
+        assert_eq!(pieces.last().unwrap().upper, u128::MAX);
+        assert!(split_ranges(&[], 4).is_empty());
+    }
+}
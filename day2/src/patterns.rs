@@ -0,0 +1,482 @@
+use crate::validator::IdValidator;
+use crate::{check_radix, to_digits};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// The digits of id in the radix as numbers, most significant first
+fn digit_values(id: u128, radix: u32) -> Vec<u32> {
+    to_digits(id, radix)
+        .chars()
+        .map(|c| c.to_digit(radix).unwrap())
+        .collect()
+}
+
+// IDs that read the same backwards, single digits included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palindrome {
+    radix: u32,
+}
+
+impl Palindrome {
+    pub fn new(radix: u32) -> Palindrome {
+        check_radix(radix);
+
+        Palindrome { radix }
+    }
+
+    // The palindrome of `digits` digits starting with the digits of half, None if it doesn't fit in a u128
+    fn mirror_half(&self, half: u128, digits: u32) -> Option<u128> {
+        let base = self.radix as u128;
+        let mut palindrome = half;
+        let mut rest = if digits.is_multiple_of(2) {
+            half
+        } else {
+            half / base
+        };
+
+        while rest > 0 {
+            palindrome = palindrome.checked_mul(base)?.checked_add(rest % base)?;
+            rest /= base;
+        }
+
+        Some(palindrome)
+    }
+}
+
+impl IdValidator for Palindrome {
+    fn is_invalid(&self, id: u128) -> bool {
+        let digits = to_digits(id, self.radix).into_bytes();
+
+        digits.iter().eq(digits.iter().rev())
+    }
+
+    // A palindrome is fixed by its first half, so only about the square root of the IDs in
+    // the range are looked at
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        let base = self.radix as u128;
+        let min_digits = lower.max(1).ilog(base) + 1;
+        let max_digits = upper.max(1).ilog(base) + 1;
+
+        let mut sum: u128 = 0;
+
+        for digits in min_digits..=max_digits {
+            let half_digits = digits.div_ceil(2);
+            let last_half = base.checked_pow(half_digits).map_or(u128::MAX, |p| p - 1);
+            let mut half = base.pow(half_digits - 1);
+
+            if digits == min_digits {
+                half = half.max(lower / base.pow(digits - half_digits));
+            }
+
+            while half <= last_half {
+                match self.mirror_half(half, digits) {
+                    Some(palindrome) if palindrome <= upper => {
+                        if palindrome >= lower {
+                            sum = sum.checked_add(palindrome)?;
+                        }
+                    }
+                    _ => break,
+                }
+                half += 1;
+            }
+        }
+
+        Some(sum)
+    }
+}
+
+// A pattern found by reading the digits one at a time, most significant first.
+// Once found it stays found, whatever digits come after.
+trait DigitPattern {
+    type State: Copy + Eq + Hash;
+
+    fn start(&self) -> Self::State;
+    fn next(&self, state: Self::State, digit: u32) -> Self::State;
+    fn found(&self, state: Self::State) -> bool;
+}
+
+fn matches<P: DigitPattern>(pattern: &P, id: u128, radix: u32) -> bool {
+    let state = digit_values(id, radix)
+        .into_iter()
+        .fold(pattern.start(), |state, digit| pattern.next(state, digit));
+
+    pattern.found(state)
+}
+
+// Known suffixes by how many digits are left and the state before them
+type SuffixMemo<S> = HashMap<(u32, S), Option<(u128, u128)>>;
+
+// Number and sum of the digit strings of length `rest` that, read after reaching `state`, end with
+// the pattern found. The strings may start with zeroes since they come after other digits.
+fn suffixes<P: DigitPattern>(
+    pattern: &P,
+    radix: u32,
+    rest: u32,
+    state: P::State,
+    memo: &mut SuffixMemo<P::State>,
+) -> Option<(u128, u128)> {
+    if rest == 0 {
+        return Some(if pattern.found(state) { (1, 0) } else { (0, 0) });
+    }
+
+    if let Some(known) = memo.get(&(rest, state)) {
+        return *known;
+    }
+
+    let place = (radix as u128).checked_pow(rest - 1);
+    let mut total = Some((0_u128, 0_u128));
+
+    for digit in 0..radix {
+        total = total.and_then(|(count, sum)| {
+            let (more, more_sum) =
+                suffixes(pattern, radix, rest - 1, pattern.next(state, digit), memo)?;
+            let leading = (digit as u128).checked_mul(place?)?.checked_mul(more)?;
+
+            Some((
+                count.checked_add(more)?,
+                sum.checked_add(leading)?.checked_add(more_sum)?,
+            ))
+        });
+    }
+
+    memo.insert((rest, state), total);
+    total
+}
+
+// Sum of the IDs in lower..=upper with the pattern found, without looking at each ID. The range is
+// cut into blocks of IDs that share a prefix and then have any digits in the last places, and each
+// block is summed from the suffixes after its prefix.
+fn sum_with_pattern<P: DigitPattern>(
+    pattern: &P,
+    lower: u128,
+    upper: u128,
+    radix: u32,
+) -> Option<u128> {
+    let base = radix as u128;
+    let mut memo = HashMap::new();
+    let mut sum: u128 = 0;
+    // 0 doesn't add anything to the sum
+    let mut start = lower.max(1);
+
+    while start <= upper {
+        // One digit length at a time, so the prefixes never have leading zeroes
+        let digits = start.ilog(base) + 1;
+        let end = upper.min(base.checked_pow(digits).map_or(u128::MAX, |p| p - 1));
+        let mut block_start = start;
+
+        loop {
+            let mut rest = 0;
+
+            while rest + 1 < digits {
+                let size = base.pow(rest + 1);
+
+                if !block_start.is_multiple_of(size)
+                    || block_start
+                        .checked_add(size - 1)
+                        .is_none_or(|last| last > end)
+                {
+                    break;
+                }
+                rest += 1;
+            }
+
+            let size = base.pow(rest);
+            let prefix = block_start / size;
+            let state = digit_values(prefix, radix)
+                .into_iter()
+                .fold(pattern.start(), |state, digit| pattern.next(state, digit));
+            let (count, rest_sum) = suffixes(pattern, radix, rest, state, &mut memo)?;
+
+            sum = sum
+                .checked_add(block_start.checked_mul(count)?)?
+                .checked_add(rest_sum)?;
+
+            let block_end = block_start + (size - 1);
+            if block_end >= end {
+                break;
+            }
+            block_start = block_end + 1;
+        }
+
+        if end >= upper {
+            break;
+        }
+        start = end + 1;
+    }
+
+    Some(sum)
+}
+
+// IDs with a run of more than `longer_than` of the same digit, like 1000 for runs longer than 2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DigitRun {
+    longer_than: u32,
+    radix: u32,
+}
+
+impl DigitRun {
+    pub fn new(longer_than: u32, radix: u32) -> DigitRun {
+        assert!(longer_than > 0, "every ID has a run of at least one digit");
+        check_radix(radix);
+
+        DigitRun { longer_than, radix }
+    }
+}
+
+impl DigitPattern for DigitRun {
+    // Last digit and how many times in a row it came, or None once a long run was found
+    type State = Option<(u32, u32)>;
+
+    fn start(&self) -> Option<(u32, u32)> {
+        Some((self.radix, 0))
+    }
+
+    fn next(&self, state: Option<(u32, u32)>, digit: u32) -> Option<(u32, u32)> {
+        let (last, run) = state?;
+        let run = if digit == last { run + 1 } else { 1 };
+
+        if run > self.longer_than {
+            None
+        } else {
+            Some((digit, run))
+        }
+    }
+
+    fn found(&self, state: Option<(u32, u32)>) -> bool {
+        state.is_none()
+    }
+}
+
+impl IdValidator for DigitRun {
+    fn is_invalid(&self, id: u128) -> bool {
+        matches(self, id, self.radix)
+    }
+
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        sum_with_pattern(self, lower, upper, self.radix)
+    }
+}
+
+// IDs with the given digits somewhere in them, e.g. 666. The digits are matched with a KMP table,
+// so the state is just how much of them has been seen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contains {
+    digits: Vec<u32>,
+    // Length of the longest proper prefix of digits[..=i] that is also a suffix of it
+    fallback: Vec<usize>,
+    radix: u32,
+}
+
+impl Contains {
+    // None if the digits are empty or not digits in the radix
+    pub fn new(digits: &str, radix: u32) -> Option<Contains> {
+        check_radix(radix);
+
+        let digits: Vec<u32> = digits
+            .chars()
+            .map(|c| c.to_digit(radix))
+            .collect::<Option<_>>()?;
+
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut fallback = vec![0; digits.len()];
+        let mut matched = 0;
+
+        for i in 1..digits.len() {
+            while matched > 0 && digits[i] != digits[matched] {
+                matched = fallback[matched - 1];
+            }
+            if digits[i] == digits[matched] {
+                matched += 1;
+            }
+            fallback[i] = matched;
+        }
+
+        Some(Contains {
+            digits,
+            fallback,
+            radix,
+        })
+    }
+}
+
+impl DigitPattern for Contains {
+    type State = usize;
+
+    fn start(&self) -> usize {
+        0
+    }
+
+    fn next(&self, mut matched: usize, digit: u32) -> usize {
+        if matched == self.digits.len() {
+            return matched;
+        }
+
+        while matched > 0 && digit != self.digits[matched] {
+            matched = self.fallback[matched - 1];
+        }
+
+        if digit == self.digits[matched] {
+            matched + 1
+        } else {
+            0
+        }
+    }
+
+    fn found(&self, matched: usize) -> bool {
+        matched == self.digits.len()
+    }
+}
+
+impl IdValidator for Contains {
+    fn is_invalid(&self, id: u128) -> bool {
+        matches(self, id, self.radix)
+    }
+
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        sum_with_pattern(self, lower, upper, self.radix)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validator::parse_rule;
+
+    fn sum_by_id<V: IdValidator>(validator: &V, lower: u128, upper: u128) -> Option<u128> {
+        let by_id = |id| validator.is_invalid(id);
+        by_id.sum_invalid(lower, upper)
+    }
+
+    const RANGES: [(u128, u128); 5] = [
+        (0, 3000),
+        (95, 115),
+        (9990, 10101),
+        (121000, 125000),
+        (7, 7),
+    ];
+
+    #[test]
+    fn palindromes() {
+        let palindrome = Palindrome::new(10);
+
+        assert!(palindrome.is_invalid(7));
+        assert!(palindrome.is_invalid(1221));
+        assert!(palindrome.is_invalid(12321));
+        assert!(!palindrome.is_invalid(1231));
+        assert!(Palindrome::new(16).is_invalid(0xabba));
+        assert!(!Palindrome::new(10).is_invalid(0xabba));
+
+        assert_eq!(palindrome.sum_invalid(95, 115), Some(99 + 101 + 111));
+
+        for radix in [2, 10, 16] {
+            let palindrome = Palindrome::new(radix);
+
+            for (lower, upper) in RANGES {
+                assert_eq!(
+                    palindrome.sum_invalid(lower, upper),
+                    sum_by_id(&palindrome, lower, upper)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn digit_runs() {
+        let run = DigitRun::new(2, 10);
+
+        assert!(run.is_invalid(1000));
+        assert!(run.is_invalid(12333));
+        assert!(!run.is_invalid(1223));
+        assert!(!run.is_invalid(121212));
+
+        for radix in [2, 3, 10] {
+            for longer_than in [1, 2, 3] {
+                let run = DigitRun::new(longer_than, radix);
+
+                for (lower, upper) in RANGES {
+                    assert_eq!(run.sum_invalid(lower, upper), sum_by_id(&run, lower, upper));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forbidden_digits() {
+        let contains = Contains::new("666", 10).unwrap();
+
+        assert!(contains.is_invalid(6660));
+        assert!(contains.is_invalid(16661));
+        assert!(!contains.is_invalid(6606));
+
+        // The KMP table has to fall back properly on a partial match
+        let contains = Contains::new("1012", 10).unwrap();
+        assert!(contains.is_invalid(101012));
+        assert!(!contains.is_invalid(101102));
+
+        for (digits, radix) in [
+            ("12", 10),
+            ("0", 10),
+            ("9", 10),
+            ("1212", 10),
+            ("101", 2),
+            ("ff", 16),
+        ] {
+            let contains = Contains::new(digits, radix).unwrap();
+
+            for (lower, upper) in RANGES {
+                assert_eq!(
+                    contains.sum_invalid(lower, upper),
+                    sum_by_id(&contains, lower, upper)
+                );
+            }
+        }
+
+        assert_eq!(Contains::new("", 10), None);
+        assert_eq!(Contains::new("1a", 10), None);
+    }
+
+    #[test]
+    fn huge_ranges() {
+        // 7 followed by 37 digits, every one of them contains a 7
+        let lower = 7 * 10_u128.pow(37);
+        let upper = lower + 10_u128.pow(20);
+        let contains = Contains::new("7", 10).unwrap();
+
+        assert_eq!(contains.sum_invalid(lower, lower + 2), Some(lower * 3 + 3));
+        assert_eq!(contains.sum_invalid(lower, upper), None);
+
+        // All of these have a long run of zeroes
+        let lower = 10_u128.pow(30);
+        assert_eq!(
+            DigitRun::new(5, 10).sum_invalid(lower, lower + 5),
+            Some(lower * 6 + 15)
+        );
+        assert_eq!(DigitRun::new(1, 10).sum_invalid(0, 12), Some(11));
+    }
+
+    #[test]
+    fn rules() {
+        let sum = |rule: &str| parse_rule(rule, 10).unwrap().sum_invalid(95, 1012);
+
+        assert_eq!(sum("palindrome"), sum_by_id(&Palindrome::new(10), 95, 1012));
+        assert_eq!(
+            sum("run>2"),
+            Some(111 + 222 + 333 + 444 + 555 + 666 + 777 + 888 + 999 + 1000)
+        );
+        assert_eq!(sum("contains:10 & run>2"), Some(1000));
+        assert_eq!(
+            sum("palindrome & !part2"),
+            sum_by_id(&parse_rule("palindrome & !part2", 10).unwrap(), 95, 1012)
+        );
+
+        assert!(parse_rule("run>0", 10).is_err());
+        assert!(parse_rule("contains:", 10).is_err());
+        assert_eq!(
+            parse_rule("contains:ab", 10).err(),
+            Some("unknown rule 'contains:ab' at 0".to_string())
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use crate::to_digits;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// An inclusive range of IDs, lower is never above upper
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdRange {
+    pub lower: u128,
+    pub upper: u128,
+}
+
+impl IdRange {
+    // Like Display, but with the bounds written in the radix the input was in
+    pub fn in_radix(&self, radix: u32) -> String {
+        format!(
+            "{}-{}",
+            to_digits(self.lower, radix),
+            to_digits(self.upper, radix)
+        )
+    }
+}
+
+impl fmt::Display for IdRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.lower, self.upper)
+    }
+}
+
+// Offset is the byte offset into the whole input of the start of the offending text
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeParseError {
+    pub offset: usize,
+    pub text: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RangeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "byte {}: {} '{}'", self.offset, self.reason, self.text)
+    }
+}
+
+impl std::error::Error for RangeParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnError {
+    // Leave out bad ranges and keep going, they end up in ParsedRanges::skipped
+    Skip,
+    // Stop at the first bad range
+    Abort,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedRanges {
+    pub ranges: Vec<IdRange>,
+    pub skipped: Vec<RangeParseError>,
+}
+
+// Ranges like "11-22", separated by commas or newlines. Reversed ranges like "22-11" and ranges
+// overlapping an earlier one are errors unless allowed. As an iterator it gives every range or
+// error in order, so the caller can decide what to do with each.
+pub struct RangeParser<'a> {
+    input: &'a str,
+    pos: usize,
+    radix: u32,
+    allow_reversed: bool,
+    allow_overlapping: bool,
+    // Accepted ranges by lower bound, only kept to check for overlaps
+    accepted: BTreeMap<u128, u128>,
+}
+
+impl<'a> RangeParser<'a> {
+    pub fn new(input: &'a str, radix: u32) -> RangeParser<'a> {
+        RangeParser {
+            input,
+            pos: 0,
+            radix,
+            allow_reversed: false,
+            allow_overlapping: false,
+            accepted: BTreeMap::new(),
+        }
+    }
+
+    // Reads "22-11" as 11-22
+    pub fn allow_reversed(mut self) -> RangeParser<'a> {
+        self.allow_reversed = true;
+        self
+    }
+
+    pub fn allow_overlapping(mut self) -> RangeParser<'a> {
+        self.allow_overlapping = true;
+        self
+    }
+
+    pub fn parse(self, on_error: OnError) -> Result<ParsedRanges, RangeParseError> {
+        let mut parsed = ParsedRanges {
+            ranges: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        for result in self {
+            match (result, on_error) {
+                (Ok(range), _) => parsed.ranges.push(range),
+                (Err(err), OnError::Skip) => parsed.skipped.push(err),
+                (Err(err), OnError::Abort) => return Err(err),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn parse_bound(&self, text: &str, offset: usize) -> Result<u128, RangeParseError> {
+        let error = |reason| RangeParseError {
+            offset,
+            text: text.to_string(),
+            reason,
+        };
+
+        // from_str_radix would take a leading +, which isn't an ID
+        if !text.chars().all(|c| c.is_digit(self.radix)) {
+            return Err(error("invalid number"));
+        }
+
+        u128::from_str_radix(text, self.radix).map_err(|_| error("number too large"))
+    }
+
+    fn parse_range(&mut self, entry: &str, offset: usize) -> Result<IdRange, RangeParseError> {
+        let error = |reason| RangeParseError {
+            offset,
+            text: entry.to_string(),
+            reason,
+        };
+
+        let Some((low, high)) = entry.split_once('-') else {
+            return Err(error("missing '-' in"));
+        };
+
+        let low_text = low.trim();
+        let low_offset = offset + (low.len() - low.trim_start().len());
+        let high_text = high.trim();
+        let high_offset = offset + low.len() + 1 + (high.len() - high.trim_start().len());
+
+        if low_text.is_empty() {
+            return Err(error("missing lower bound in"));
+        }
+        if high_text.is_empty() {
+            return Err(error("missing upper bound in"));
+        }
+
+        let mut lower = self.parse_bound(low_text, low_offset)?;
+        let mut upper = self.parse_bound(high_text, high_offset)?;
+
+        if lower > upper {
+            if !self.allow_reversed {
+                return Err(error("reversed range"));
+            }
+            std::mem::swap(&mut lower, &mut upper);
+        }
+
+        if !self.allow_overlapping {
+            // Accepted ranges never overlap each other, so only the closest one starting at or
+            // below upper can overlap this one
+            if let Some((_, &end)) = self.accepted.range(..=upper).next_back()
+                && end >= lower
+            {
+                return Err(error("range overlaps an earlier one"));
+            }
+            self.accepted.insert(lower, upper);
+        }
+
+        Ok(IdRange { lower, upper })
+    }
+}
+
+impl Iterator for RangeParser<'_> {
+    type Item = Result<IdRange, RangeParseError>;
+
+    fn next(&mut self) -> Option<Result<IdRange, RangeParseError>> {
+        while self.pos < self.input.len() {
+            let rest = &self.input[self.pos..];
+            let len = rest.find([',', '\n']).unwrap_or(rest.len());
+            let entry = &rest[..len];
+            let offset = self.pos + (entry.len() - entry.trim_start().len());
+
+            self.pos += (len + 1).min(rest.len());
+
+            // Blank lines and trailing commas
+            if entry.trim().is_empty() {
+                continue;
+            }
+
+            return Some(self.parse_range(entry.trim(), offset));
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedRanges {
+    // Sorted and disjoint, touching ranges are merged too
+    pub ranges: Vec<IdRange>,
+    // Pairs of input ranges sharing at least one ID, as indices into the input
+    pub overlaps: Vec<(usize, usize)>,
+}
+
+// Sorts and merges the ranges so every ID is in at most one of them
+pub fn normalize(ranges: &[IdRange]) -> NormalizedRanges {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|&i| (ranges[i].lower, ranges[i].upper));
+
+    let mut merged: Vec<IdRange> = Vec::new();
+    let mut overlaps = Vec::new();
+    // Earlier ranges (in sorted order) that might still reach the current one
+    let mut open: Vec<usize> = Vec::new();
+
+    for i in order {
+        let range = ranges[i];
+
+        open.retain(|&j| ranges[j].upper >= range.lower);
+        overlaps.extend(open.iter().map(|&j| (j.min(i), j.max(i))));
+        open.push(i);
+
+        match merged.last_mut() {
+            Some(last) if range.lower <= last.upper.saturating_add(1) => {
+                last.upper = last.upper.max(range.upper)
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    overlaps.sort();
+
+    NormalizedRanges {
+        ranges: merged,
+        overlaps,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn range(lower: u128, upper: u128) -> IdRange {
+        IdRange { lower, upper }
+    }
+
+    fn parse(input: &str) -> Result<Vec<IdRange>, RangeParseError> {
+        RangeParser::new(input, 10)
+            .parse(OnError::Abort)
+            .map(|parsed| parsed.ranges)
+    }
+
+    #[test]
+    fn commas_and_newlines() {
+        let ranges = parse("11-22,95-115,\n998-1012\r\n 1188511880 - 1188511890 ,\n\n").unwrap();
+
+        assert_eq!(
+            ranges,
+            vec![
+                range(11, 22),
+                range(95, 115),
+                range(998, 1012),
+                range(1188511880, 1188511890)
+            ]
+        );
+    }
+
+    #[test]
+    fn sample_input() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+
+        assert_eq!(parse(&input).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn error_offsets() {
+        let err = parse("11-22,  1234,5-6").unwrap_err();
+        assert_eq!((err.offset, err.reason), (8, "missing '-' in"));
+        assert_eq!(err.to_string(), "byte 8: missing '-' in '1234'");
+
+        let err = parse("11-22,33-4x4").unwrap_err();
+        assert_eq!((err.offset, err.text.as_str()), (9, "4x4"));
+        assert_eq!(err.reason, "invalid number");
+
+        let err = parse("11-22\n-5").unwrap_err();
+        assert_eq!((err.offset, err.reason), (6, "missing lower bound in"));
+
+        let err = parse("7- ").unwrap_err();
+        assert_eq!((err.offset, err.reason), (0, "missing upper bound in"));
+
+        let err = parse("1-+5").unwrap_err();
+        assert_eq!((err.offset, err.reason), (2, "invalid number"));
+
+        let err = parse("1-999999999999999999999999999999999999999").unwrap_err();
+        assert_eq!(err.reason, "number too large");
+    }
+
+    #[test]
+    fn reversed_ranges() {
+        let err = parse("1-5,22-11").unwrap_err();
+        assert_eq!((err.offset, err.reason), (4, "reversed range"));
+
+        let parsed = RangeParser::new("22-11", 10)
+            .allow_reversed()
+            .parse(OnError::Abort)
+            .unwrap();
+        assert_eq!(parsed.ranges, vec![range(11, 22)]);
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let err = parse("1-5,10-20,21-30,15-15").unwrap_err();
+        assert_eq!(
+            (err.offset, err.reason),
+            (16, "range overlaps an earlier one")
+        );
+
+        // Touching is fine, sharing an ID is not
+        assert!(parse("1-5,6-10").is_ok());
+        assert!(parse("6-10,1-6").is_err());
+
+        let parsed = RangeParser::new("1-5,3-8", 10)
+            .allow_overlapping()
+            .parse(OnError::Abort)
+            .unwrap();
+        assert_eq!(parsed.ranges, vec![range(1, 5), range(3, 8)]);
+    }
+
+    #[test]
+    fn skip_bad_ranges() {
+        let parsed = RangeParser::new("1-5,x,9-7,3-4,10-12", 10)
+            .parse(OnError::Skip)
+            .unwrap();
+
+        assert_eq!(parsed.ranges, vec![range(1, 5), range(10, 12)]);
+        assert_eq!(
+            parsed
+                .skipped
+                .iter()
+                .map(|err| err.offset)
+                .collect::<Vec<_>>(),
+            vec![4, 6, 10]
+        );
+    }
+
+    #[test]
+    fn normalize_merges_and_reports_overlaps() {
+        let ranges = [
+            range(30, 40),
+            range(1, 5),
+            range(35, 50),
+            range(6, 10),
+            range(1, 5),
+            range(38, 39),
+            range(60, 60),
+        ];
+
+        let normalized = normalize(&ranges);
+
+        assert_eq!(
+            normalized.ranges,
+            vec![range(1, 10), range(30, 50), range(60, 60)]
+        );
+        // 6-10 only touches 1-5, it doesn't share any IDs with it
+        assert_eq!(normalized.overlaps, vec![(0, 2), (0, 5), (1, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn normalize_up_to_the_largest_id() {
+        let normalized = normalize(&[range(u128::MAX, u128::MAX), range(5, u128::MAX)]);
+
+        assert_eq!(normalized.ranges, vec![range(5, u128::MAX)]);
+        assert_eq!(normalized.overlaps, vec![(0, 1)]);
+        assert_eq!(normalize(&[]).ranges, vec![]);
+    }
+
+    #[test]
+    fn ranges_in_hex() {
+        assert_eq!(range(10, 31).in_radix(16), "a-1f");
+
+        let ranges = RangeParser::new("a-1F,ff-100", 16)
+            .parse(OnError::Abort)
+            .unwrap()
+            .ranges;
+
+        assert_eq!(ranges, vec![range(10, 31), range(255, 256)]);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::validator::IdValidator;
+use crate::{check_radix, sum_by_smallest_period, to_digits};
+use std::collections::BTreeSet;
+
+// Length of the smallest block of digits the ID is made of, in the radix. An ID that isn't
+// repeated at all is its own block, so 123123123 gives 3 and 1234 gives 4.
+pub fn minimal_period(id: u128, radix: u32) -> u32 {
+    let digits = to_digits(id, radix).into_bytes();
+    let len = digits.len();
+
+    (1..=len)
+        .find(|p| len.is_multiple_of(*p) && digits[*p..] == digits[..len - p])
+        .unwrap() as u32
+}
+
+// Whether the ID is some block of digits repeated exactly `times` times. The block doesn't have
+// to be the smallest one, 1111 is 11 twice as well as 1 four times.
+pub fn repeats_exactly(id: u128, times: u32, radix: u32) -> bool {
+    let digits = to_digits(id, radix).len() as u32;
+
+    digits.is_multiple_of(times) && (digits / times).is_multiple_of(minimal_period(id, radix))
+}
+
+// IDs repeated any of the given number of times, e.g. only 3 or 5 times
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatCounts {
+    counts: BTreeSet<u32>,
+    radix: u32,
+}
+
+impl RepeatCounts {
+    pub fn new(counts: &[u32], radix: u32) -> RepeatCounts {
+        assert!(!counts.contains(&0), "an ID can't be repeated 0 times");
+        check_radix(radix);
+
+        RepeatCounts {
+            counts: counts.iter().copied().collect(),
+            radix,
+        }
+    }
+}
+
+impl IdValidator for RepeatCounts {
+    fn is_invalid(&self, id: u128) -> bool {
+        self.counts
+            .iter()
+            .any(|times| repeats_exactly(id, *times, self.radix))
+    }
+
+    // An ID of `digits` digits with smallest period p is repeated k times for every k dividing
+    // digits / p, so the IDs are summed by their smallest period like sum_repeated_ids does.
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        let base = self.radix as u128;
+        let min_digits = lower.max(1).ilog(base) + 1;
+        let max_digits = upper.max(1).ilog(base) + 1;
+
+        let mut sum: u128 = 0;
+
+        for digits in min_digits..=max_digits {
+            for period in (1..=digits).filter(|p| digits.is_multiple_of(*p)) {
+                let repeats = digits / period;
+
+                if !self
+                    .counts
+                    .iter()
+                    .any(|times| repeats.is_multiple_of(*times))
+                {
+                    continue;
+                }
+
+                let ids = sum_by_smallest_period(lower, upper, self.radix, digits, period)?;
+                sum = sum.checked_add(ids)?;
+            }
+        }
+
+        Some(sum)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ranges::{OnError, RangeParser, normalize};
+    use crate::{RepeatRule, is_invalid_id_part_1, sum_invalid_ids};
+
+    #[test]
+    fn minimal_periods() {
+        assert_eq!(minimal_period(123123123, 10), 3);
+        assert_eq!(minimal_period(1111111, 10), 1);
+        assert_eq!(minimal_period(1212121212, 10), 2);
+        assert_eq!(minimal_period(1234, 10), 4);
+        assert_eq!(minimal_period(7, 10), 1);
+        assert_eq!(minimal_period(0xabab, 16), 2);
+        assert_eq!(minimal_period(0b101101, 2), 3);
+    }
+
+    #[test]
+    fn exact_repeats() {
+        assert!(repeats_exactly(1111, 2, 10));
+        assert!(repeats_exactly(1111, 4, 10));
+        assert!(!repeats_exactly(1111, 3, 10));
+        assert!(repeats_exactly(123123123, 3, 10));
+        assert!(!repeats_exactly(123123123, 2, 10));
+        assert!(repeats_exactly(1234, 1, 10));
+
+        // Exactly twice is part 1
+        for id in 1..5000 {
+            assert_eq!(
+                repeats_exactly(id, 2, 10),
+                is_invalid_id_part_1(id, 10, false)
+            );
+        }
+    }
+
+    #[test]
+    fn closed_form_matches_checking_every_id() {
+        let count_sets: [&[u32]; 5] = [&[1], &[2], &[3, 5], &[2, 3], &[4]];
+
+        for radix in [2, 10] {
+            for counts in count_sets {
+                let validator = RepeatCounts::new(counts, radix);
+                let by_id = |id| validator.is_invalid(id);
+
+                for (lower, upper) in [(0, 2000), (95, 115), (110000, 112000), (999999, 1001000)] {
+                    assert_eq!(
+                        validator.sum_invalid(lower, upper),
+                        by_id.sum_invalid(lower, upper)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sample_input_by_repeat_count() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let parsed = RangeParser::new(&input, 10).parse(OnError::Abort).unwrap();
+        let ranges = normalize(&parsed.ranges).ranges;
+        let sum = |counts: &[u32]| sum_invalid_ids(&ranges, &RepeatCounts::new(counts, 10));
+
+        let all_counts: Vec<u32> = (2..=20).collect();
+
+        assert_eq!(sum(&[2]), sum_invalid_ids(&ranges, &RepeatRule::Twice));
+        assert_eq!(sum(&all_counts), Some(4174379265));
+        // 111, 999, 222222, 565656 and 824824824 three times, 2121212121 five times
+        assert_eq!(
+            sum(&[3, 5]),
+            Some(111 + 999 + 222222 + 565656 + 824824824 + 2121212121)
+        );
+    }
+}
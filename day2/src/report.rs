@@ -0,0 +1,333 @@
+use crate::ranges::IdRange;
+use crate::{RepeatRule, blocks_with_period, format_sum, to_digits};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// An invalid ID along with the block of digits it repeats, e.g. 123123123 is 123 three times
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedId {
+    pub id: u128,
+    pub unit: String,
+    pub repeats: u32,
+}
+
+// Every repeated ID in lower..=upper under the rule, in order. Built from the blocks instead of
+// checking each ID, and part 2 IDs are given with their smallest unit, like 1111111 is 1 seven times.
+pub fn repeated_ids(lower: u128, upper: u128, radix: u32, rule: RepeatRule) -> Vec<RepeatedId> {
+    let base = radix as u128;
+    let min_digits = lower.max(1).ilog(base) + 1;
+    let max_digits = upper.max(1).ilog(base) + 1;
+
+    let mut ids = BTreeMap::new();
+
+    for digits in min_digits.max(2)..=max_digits {
+        let periods: Vec<u32> = match rule {
+            RepeatRule::Twice if digits.is_multiple_of(2) => vec![digits / 2],
+            RepeatRule::Twice => vec![],
+            RepeatRule::AtLeastTwice => (1..digits).filter(|p| digits.is_multiple_of(*p)).collect(),
+        };
+
+        // Smallest periods first, so an ID keeps the smallest unit it was found with
+        for period in periods {
+            let Some((multiplier, first_block, last_block)) =
+                blocks_with_period(lower, upper, radix, digits, period)
+            else {
+                continue;
+            };
+
+            for block in first_block..=last_block {
+                ids.entry(block * multiplier).or_insert(RepeatedId {
+                    id: block * multiplier,
+                    unit: to_digits(block, radix),
+                    repeats: digits / period,
+                });
+            }
+        }
+    }
+
+    ids.into_values().collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeReport {
+    pub lower: u128,
+    pub upper: u128,
+    pub part_1: Vec<RepeatedId>,
+    pub part_2: Vec<RepeatedId>,
+}
+
+// What the puzzle text shows for the example: each range with the invalid IDs it has under both rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub radix: u32,
+    pub ranges: Vec<RangeReport>,
+}
+
+impl Report {
+    pub fn new(ranges: &[IdRange], radix: u32) -> Report {
+        let ranges = ranges
+            .iter()
+            .map(|&IdRange { lower, upper }| RangeReport {
+                lower,
+                upper,
+                part_1: repeated_ids(lower, upper, radix, RepeatRule::Twice),
+                part_2: repeated_ids(lower, upper, radix, RepeatRule::AtLeastTwice),
+            })
+            .collect();
+
+        Report { radix, ranges }
+    }
+
+    // Number of invalid IDs and their sum, for part 1 and part 2. The sum is None if it doesn't fit in a u128.
+    pub fn summary(&self) -> [(usize, Option<u128>); 2] {
+        let total = |ids: Vec<&RepeatedId>| {
+            let sum = ids
+                .iter()
+                .try_fold(0_u128, |sum, id| sum.checked_add(id.id));
+            (ids.len(), sum)
+        };
+
+        [
+            total(self.ranges.iter().flat_map(|r| &r.part_1).collect()),
+            total(self.ranges.iter().flat_map(|r| &r.part_2).collect()),
+        ]
+    }
+
+    pub fn to_json(&self) -> String {
+        let ids_json = |ids: &[RepeatedId]| {
+            let ids: Vec<String> = ids
+                .iter()
+                .map(|id| {
+                    format!(
+                        "{{\"id\":{},\"unit\":\"{}\",\"repeats\":{}}}",
+                        id.id, id.unit, id.repeats
+                    )
+                })
+                .collect();
+
+            format!("[{}]", ids.join(","))
+        };
+
+        let ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|range| {
+                format!(
+                    "{{\"lower\":{},\"upper\":{},\"part_1\":{},\"part_2\":{}}}",
+                    range.lower,
+                    range.upper,
+                    ids_json(&range.part_1),
+                    ids_json(&range.part_2)
+                )
+            })
+            .collect();
+
+        let [(count_1, sum_1), (count_2, sum_2)] = self.summary();
+        let sum_json = |sum: Option<u128>| sum.map_or("null".to_string(), |sum| sum.to_string());
+
+        format!(
+            "{{\"radix\":{},\"ranges\":[{}],\"summary\":{{\"part_1\":{{\"count\":{},\"sum\":{}}},\"part_2\":{{\"count\":{},\"sum\":{}}}}}}}",
+            self.radix,
+            ranges.join(","),
+            count_1,
+            sum_json(sum_1),
+            count_2,
+            sum_json(sum_2)
+        )
+    }
+}
+
+// One row per invalid ID, ranges without any get a single row of dashes. IDs are written in the radix.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>25} | {:>6} | {:>20} | {:>10} | {:>7}",
+            "range", "rule", "id", "unit", "repeats"
+        )?;
+
+        for range in &self.ranges {
+            let name = format!(
+                "{}-{}",
+                to_digits(range.lower, self.radix),
+                to_digits(range.upper, self.radix)
+            );
+
+            for (rule, ids) in [("part 1", &range.part_1), ("part 2", &range.part_2)] {
+                if ids.is_empty() {
+                    writeln!(
+                        f,
+                        "{:>25} | {:>6} | {:>20} | {:>10} | {:>7}",
+                        name, rule, "-", "-", "-"
+                    )?;
+                }
+
+                for id in ids {
+                    writeln!(
+                        f,
+                        "{:>25} | {:>6} | {:>20} | {:>10} | {:>7}",
+                        name,
+                        rule,
+                        to_digits(id.id, self.radix),
+                        id.unit,
+                        id.repeats
+                    )?;
+                }
+            }
+        }
+
+        let [(count_1, sum_1), (count_2, sum_2)] = self.summary();
+        writeln!(
+            f,
+            "part 1: {} invalid IDs, sum {}",
+            count_1,
+            format_sum(sum_1)
+        )?;
+        writeln!(
+            f,
+            "part 2: {} invalid IDs, sum {}",
+            count_2,
+            format_sum(sum_2)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ranges::{OnError, RangeParser, normalize};
+    use crate::{is_invalid_id_part_1, is_invalid_id_part_2};
+
+    fn sample_report() -> Report {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let parsed = RangeParser::new(&input, 10).parse(OnError::Abort).unwrap();
+        Report::new(&parsed.ranges, 10)
+    }
+
+    fn ids(ids: &[RepeatedId]) -> Vec<(u128, &str, u32)> {
+        ids.iter()
+            .map(|id| (id.id, id.unit.as_str(), id.repeats))
+            .collect()
+    }
+
+    #[test]
+    fn sample_report_matches_puzzle_text() {
+        let report = sample_report();
+
+        assert_eq!(ids(&report.ranges[0].part_1), [(11, "1", 2), (22, "2", 2)]);
+        assert_eq!(ids(&report.ranges[1].part_1), [(99, "9", 2)]);
+        assert_eq!(ids(&report.ranges[1].part_2), [(99, "9", 2), (111, "1", 3)]);
+        assert_eq!(
+            ids(&report.ranges[2].part_2),
+            [(999, "9", 3), (1010, "10", 2)]
+        );
+        assert!(report.ranges[5].part_2.is_empty());
+        assert_eq!(ids(&report.ranges[9].part_2), [(824824824, "824", 3)]);
+        assert_eq!(ids(&report.ranges[10].part_2), [(2121212121, "21", 5)]);
+
+        assert_eq!(
+            report.summary(),
+            [(8, Some(1227775554)), (13, Some(4174379265))]
+        );
+    }
+
+    #[test]
+    fn touching_ranges_are_separate_rows() {
+        let parsed = RangeParser::new("50-82,83-113", 10)
+            .parse(OnError::Abort)
+            .unwrap();
+        let report = Report::new(&parsed.ranges, 10);
+
+        // Summing merges them, the report still has a row for each range in the input
+        assert_eq!(normalize(&parsed.ranges).ranges.len(), 1);
+        assert_eq!(report.ranges.len(), 2);
+        assert_eq!(
+            ids(&report.ranges[0].part_1),
+            [(55, "5", 2), (66, "6", 2), (77, "7", 2)]
+        );
+        assert_eq!(
+            ids(&report.ranges[1].part_2),
+            [(88, "8", 2), (99, "9", 2), (111, "1", 3)]
+        );
+
+        let table = report.to_string();
+        assert!(table.contains("50-82 | part 1 |"));
+        assert!(table.contains("83-113 | part 1 |"));
+    }
+
+    #[test]
+    fn smallest_unit_for_part_2() {
+        let part_1 = repeated_ids(1111, 1111, 10, RepeatRule::Twice);
+        let part_2 = repeated_ids(1111, 1111, 10, RepeatRule::AtLeastTwice);
+
+        assert_eq!(ids(&part_1), [(1111, "11", 2)]);
+        assert_eq!(ids(&part_2), [(1111, "1", 4)]);
+    }
+
+    #[test]
+    fn listing_matches_validators() {
+        for radix in [2, 10, 16] {
+            for (lower, upper) in [(0, 3000), (95, 115), (121000, 125000)] {
+                let part_1: Vec<u128> = repeated_ids(lower, upper, radix, RepeatRule::Twice)
+                    .iter()
+                    .map(|id| id.id)
+                    .collect();
+                let part_2: Vec<u128> = repeated_ids(lower, upper, radix, RepeatRule::AtLeastTwice)
+                    .iter()
+                    .map(|id| id.id)
+                    .collect();
+
+                let expected_1: Vec<u128> = (lower..=upper)
+                    .filter(|id| is_invalid_id_part_1(*id, radix, false))
+                    .collect();
+                let expected_2: Vec<u128> = (lower..=upper)
+                    .filter(|id| is_invalid_id_part_2(*id, radix, false))
+                    .collect();
+
+                assert_eq!(part_1, expected_1);
+                assert_eq!(part_2, expected_2);
+            }
+        }
+    }
+
+    #[test]
+    fn units_in_hex() {
+        let report = Report::new(
+            &[IdRange {
+                lower: 0xab00,
+                upper: 0xac00,
+            }],
+            16,
+        );
+
+        assert_eq!(ids(&report.ranges[0].part_1), [(0xabab, "ab", 2)]);
+        assert!(report.to_string().contains("ab00-ac00 | part 1 |"));
+    }
+
+    #[test]
+    fn json_output() {
+        let report = Report::new(
+            &[
+                IdRange {
+                    lower: 11,
+                    upper: 22,
+                },
+                IdRange {
+                    lower: 1698522,
+                    upper: 1698528,
+                },
+            ],
+            10,
+        );
+
+        assert_eq!(
+            report.to_json(),
+            "{\"radix\":10,\"ranges\":[\
+             {\"lower\":11,\"upper\":22,\
+             \"part_1\":[{\"id\":11,\"unit\":\"1\",\"repeats\":2},{\"id\":22,\"unit\":\"2\",\"repeats\":2}],\
+             \"part_2\":[{\"id\":11,\"unit\":\"1\",\"repeats\":2},{\"id\":22,\"unit\":\"2\",\"repeats\":2}]},\
+             {\"lower\":1698522,\"upper\":1698528,\"part_1\":[],\"part_2\":[]}],\
+             \"summary\":{\"part_1\":{\"count\":2,\"sum\":33},\"part_2\":{\"count\":2,\"sum\":33}}}"
+        );
+    }
+}
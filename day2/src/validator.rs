@@ -0,0 +1,312 @@
+use crate::patterns::{Contains, DigitRun, Palindrome};
+use crate::{
+    RepeatRule, check_radix, is_invalid_id_part_1, is_invalid_id_part_2, series_sum,
+    sum_repeated_ids,
+};
+
+pub trait IdValidator {
+    fn is_invalid(&self, id: u128) -> bool;
+
+    // Sum of the invalid IDs in lower..=upper, None if it doesn't fit in a u128. Checks every ID
+    // unless the validator knows better.
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        (lower..=upper)
+            .filter(|id| self.is_invalid(*id))
+            .try_fold(0_u128, |sum, id| sum.checked_add(id))
+    }
+
+    fn and<V: IdValidator>(self, other: V) -> And<Self, V>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<V: IdValidator>(self, other: V) -> Or<Self, V>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+// Any closure or function taking an ID is a validator, e.g. |id| is_invalid_id_part_1(id, 10, false)
+impl<F: Fn(u128) -> bool> IdValidator for F {
+    fn is_invalid(&self, id: u128) -> bool {
+        self(id)
+    }
+}
+
+// Rules are Sync so they can be checked from several threads
+impl IdValidator for Box<dyn IdValidator + Sync> {
+    fn is_invalid(&self, id: u128) -> bool {
+        self.as_ref().is_invalid(id)
+    }
+
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        self.as_ref().sum_invalid(lower, upper)
+    }
+}
+
+// A repeat rule judged on the digits of the ID in some radix from 2 to 36
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepeatedDigits {
+    rule: RepeatRule,
+    radix: u32,
+}
+
+impl RepeatedDigits {
+    pub fn new(rule: RepeatRule, radix: u32) -> RepeatedDigits {
+        check_radix(radix);
+
+        RepeatedDigits { rule, radix }
+    }
+}
+
+impl IdValidator for RepeatedDigits {
+    fn is_invalid(&self, id: u128) -> bool {
+        match self.rule {
+            RepeatRule::Twice => is_invalid_id_part_1(id, self.radix, false),
+            RepeatRule::AtLeastTwice => is_invalid_id_part_2(id, self.radix, false),
+        }
+    }
+
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        sum_repeated_ids(lower, upper, self.radix, self.rule)
+    }
+}
+
+// On its own a repeat rule means decimal digits, like in the puzzle
+impl IdValidator for RepeatRule {
+    fn is_invalid(&self, id: u128) -> bool {
+        RepeatedDigits::new(*self, 10).is_invalid(id)
+    }
+
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        RepeatedDigits::new(*self, 10).sum_invalid(lower, upper)
+    }
+}
+
+pub struct And<A, B>(A, B);
+
+impl<A: IdValidator, B: IdValidator> IdValidator for And<A, B> {
+    fn is_invalid(&self, id: u128) -> bool {
+        self.0.is_invalid(id) && self.1.is_invalid(id)
+    }
+}
+
+pub struct Or<A, B>(A, B);
+
+impl<A: IdValidator, B: IdValidator> IdValidator for Or<A, B> {
+    fn is_invalid(&self, id: u128) -> bool {
+        self.0.is_invalid(id) || self.1.is_invalid(id)
+    }
+}
+
+pub struct Not<A>(A);
+
+impl<A: IdValidator> IdValidator for Not<A> {
+    fn is_invalid(&self, id: u128) -> bool {
+        !self.0.is_invalid(id)
+    }
+
+    // Everything in the range that the inner validator doesn't count
+    fn sum_invalid(&self, lower: u128, upper: u128) -> Option<u128> {
+        Some(series_sum(lower, upper)? - self.0.sum_invalid(lower, upper)?)
+    }
+}
+
+// Rules written like "part2 & !part1", with ! binding tightest, then &, then |
+struct RuleParser<'a> {
+    rule: &'a str,
+    pos: usize,
+    radix: u32,
+}
+
+impl<'a> RuleParser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.rule[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+
+        if self.rule[self.pos..].starts_with(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn any(&mut self) -> Result<Box<dyn IdValidator + Sync>, String> {
+        let mut validator = self.all()?;
+
+        while self.eat('|') {
+            validator = Box::new(validator.or(self.all()?));
+        }
+
+        Ok(validator)
+    }
+
+    fn all(&mut self) -> Result<Box<dyn IdValidator + Sync>, String> {
+        let mut validator = self.single()?;
+
+        while self.eat('&') {
+            validator = Box::new(validator.and(self.single()?));
+        }
+
+        Ok(validator)
+    }
+
+    fn single(&mut self) -> Result<Box<dyn IdValidator + Sync>, String> {
+        if self.eat('!') {
+            return Ok(Box::new(self.single()?.not()));
+        }
+
+        if self.eat('(') {
+            let validator = self.any()?;
+
+            if !self.eat(')') {
+                return Err(format!("expected ')' at {}", self.pos));
+            }
+
+            return Ok(validator);
+        }
+
+        let start = self.pos;
+        let len = self.rule[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '>' || c == ':'))
+            .unwrap_or(self.rule.len() - start);
+        self.pos += len;
+
+        match &self.rule[start..self.pos] {
+            "part1" => Ok(Box::new(RepeatedDigits::new(RepeatRule::Twice, self.radix))),
+            "part2" => Ok(Box::new(RepeatedDigits::new(
+                RepeatRule::AtLeastTwice,
+                self.radix,
+            ))),
+            "palindrome" => Ok(Box::new(Palindrome::new(self.radix))),
+            "" => Err(format!("expected a rule at {}", start)),
+            name => {
+                let unknown = || format!("unknown rule '{}' at {}", name, start);
+
+                if let Some(longer_than) = name.strip_prefix("run>") {
+                    match longer_than.parse::<u32>() {
+                        Ok(longer_than) if longer_than > 0 => {
+                            Ok(Box::new(DigitRun::new(longer_than, self.radix)))
+                        }
+                        _ => Err(unknown()),
+                    }
+                } else if let Some(digits) = name.strip_prefix("contains:") {
+                    match Contains::new(digits, self.radix) {
+                        Some(contains) => Ok(Box::new(contains)),
+                        None => Err(unknown()),
+                    }
+                } else {
+                    Err(unknown())
+                }
+            }
+        }
+    }
+}
+
+// part1 and part2 are judged on the digits in the given radix, as are palindrome, run>N for a run
+// of more than N of the same digit and contains:DIGITS
+pub fn parse_rule(rule: &str, radix: u32) -> Result<Box<dyn IdValidator + Sync>, String> {
+    let mut parser = RuleParser {
+        rule,
+        pos: 0,
+        radix,
+    };
+    let validator = parser.any()?;
+    parser.skip_whitespace();
+
+    if parser.pos != rule.len() {
+        return Err(format!("unexpected '{}'", &rule[parser.pos..]));
+    }
+
+    Ok(validator)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ranges::{OnError, RangeParser, normalize};
+    use crate::sum_invalid_ids;
+
+    #[test]
+    fn closures_are_validators() {
+        let even = |id: u128| id.is_multiple_of(2);
+
+        assert!(even.is_invalid(4));
+        assert_eq!(even.sum_invalid(1, 10), Some(30));
+    }
+
+    #[test]
+    fn combinators() {
+        let part_2_only = RepeatRule::AtLeastTwice.and(RepeatRule::Twice.not());
+
+        assert!(part_2_only.is_invalid(111));
+        assert!(!part_2_only.is_invalid(1111));
+        assert!(!part_2_only.is_invalid(1234));
+        assert_eq!(part_2_only.sum_invalid(95, 115), Some(111));
+
+        let small_or_part_1 = (|id: u128| id < 3).or(RepeatRule::Twice);
+        assert_eq!(small_or_part_1.sum_invalid(1, 22), Some(1 + 2 + 11 + 22));
+    }
+
+    #[test]
+    fn not_sums_the_rest_of_the_range() {
+        let valid = RepeatRule::AtLeastTwice.not();
+        let valid_by_id = |id| !is_invalid_id_part_2(id, 10, false);
+
+        assert_eq!(
+            valid.sum_invalid(95, 1012),
+            valid_by_id.sum_invalid(95, 1012)
+        );
+    }
+
+    #[test]
+    fn parse_rules() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let parsed = RangeParser::new(&input, 10).parse(OnError::Abort).unwrap();
+        let ranges = normalize(&parsed.ranges).ranges;
+
+        let sum = |rule: &str| sum_invalid_ids(&ranges, &parse_rule(rule, 10).unwrap());
+
+        assert_eq!(sum("part1"), Some(1227775554));
+        assert_eq!(sum(" part2 "), Some(4174379265));
+        assert_eq!(sum("part2 & !part1"), Some(4174379265 - 1227775554));
+        assert_eq!(sum("!(!part1 & !part2)"), Some(4174379265));
+        assert_eq!(sum("part1 | part2"), Some(4174379265));
+    }
+
+    #[test]
+    fn parse_rule_errors() {
+        assert_eq!(
+            parse_rule("part3", 10).err(),
+            Some("unknown rule 'part3' at 0".to_string())
+        );
+        assert_eq!(
+            parse_rule("part1 &", 10).err(),
+            Some("expected a rule at 7".to_string())
+        );
+        assert_eq!(
+            parse_rule("(part1", 10).err(),
+            Some("expected ')' at 6".to_string())
+        );
+        assert_eq!(
+            parse_rule("part1 part2", 10).err(),
+            Some("unexpected 'part2'".to_string())
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+
+// A line of the input that is all batteries, each with a joltage rating from 1 to 9
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bank {
+    // Line in the input, counting from 1
+    pub line: usize,
+    batteries: Vec<u32>,
+}
+
+impl Bank {
+    pub fn parse(text: &str, line: usize) -> Result<Bank, BankError> {
+        let mut batteries = Vec::with_capacity(text.len());
+
+        for (i, c) in text.chars().enumerate() {
+            match c.to_digit(10) {
+                Some(rating @ 1..=9) => batteries.push(rating),
+                _ => {
+                    return Err(BankError {
+                        line,
+                        column: i + 1,
+                        reason: format!("'{}' is not a joltage rating from 1 to 9", c),
+                    });
+                }
+            }
+        }
+
+        if batteries.is_empty() {
+            return Err(BankError {
+                line,
+                column: 1,
+                reason: "bank has no batteries".to_string(),
+            });
+        }
+
+        Ok(Bank { line, batteries })
+    }
+
+    pub fn batteries(&self) -> &[u32] {
+        &self.batteries
+    }
+
+    pub fn len(&self) -> usize {
+        self.batteries.len()
+    }
+}
+
+impl fmt::Display for Bank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rating in &self.batteries {
+            write!(f, "{}", rating)?;
+        }
+        Ok(())
+    }
+}
+
+// Line and column count from 1, like an editor shows them
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankError {
+    pub line: usize,
+    pub column: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.reason
+        )
+    }
+}
+
+impl std::error::Error for BankError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Validation {
+    // Any bad bank is an error
+    Strict,
+    // Bad banks are left out and kept in ParsedBanks::skipped, so they can still be reported
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedBanks {
+    pub banks: Vec<Bank>,
+    pub skipped: Vec<BankError>,
+}
+
+// One bank per line, blank lines are left out
+pub fn parse_banks(input: &str, validation: Validation) -> Result<ParsedBanks, BankError> {
+    let mut parsed = ParsedBanks {
+        banks: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match (Bank::parse(line, i + 1), validation) {
+            (Ok(bank), _) => parsed.banks.push(bank),
+            (Err(err), Validation::Lenient) => parsed.skipped.push(err),
+            (Err(err), Validation::Strict) => return Err(err),
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_bank() {
+        let bank = Bank::parse("987654321111111", 1).unwrap();
+
+        assert_eq!(bank.len(), 15);
+        assert_eq!(bank.batteries()[..3], [9, 8, 7]);
+        assert_eq!(bank.to_string(), "987654321111111");
+    }
+
+    #[test]
+    fn bad_banks() {
+        let err = Bank::parse("8181x1", 4).unwrap_err();
+        assert_eq!((err.line, err.column), (4, 5));
+        assert_eq!(
+            err.to_string(),
+            "line 4, column 5: 'x' is not a joltage rating from 1 to 9"
+        );
+
+        let err = Bank::parse("12 3", 1).unwrap_err();
+        assert_eq!(err.column, 3);
+        assert_eq!(Bank::parse("105", 1).unwrap_err().column, 2);
+        assert_eq!(
+            Bank::parse("", 2).unwrap_err().reason,
+            "bank has no batteries"
+        );
+    }
+
+    #[test]
+    fn strict_and_lenient() {
+        let input = "987\r\n\n81a\n234\n1-1\n";
+
+        let err = parse_banks(input, Validation::Strict).unwrap_err();
+        assert_eq!((err.line, err.column), (3, 3));
+
+        let parsed = parse_banks(input, Validation::Lenient).unwrap();
+        let lines: Vec<usize> = parsed.banks.iter().map(|bank| bank.line).collect();
+        let skipped: Vec<(usize, usize)> = parsed
+            .skipped
+            .iter()
+            .map(|err| (err.line, err.column))
+            .collect();
+
+        assert_eq!(lines, [1, 4]);
+        assert_eq!(skipped, [(3, 3), (5, 2)]);
+    }
+
+    #[test]
+    fn sample_input() {
+        let input = std::fs::read_to_string("data/sample_input").unwrap();
+        let parsed = parse_banks(&input, Validation::Strict).unwrap();
+
+        assert_eq!(parsed.banks.len(), 4);
+        assert!(parsed.skipped.is_empty());
+    }
+}
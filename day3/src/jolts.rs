@@ -0,0 +1,129 @@
+use std::fmt;
+use std::ops::AddAssign;
+
+// A joltage of any number of digits. Banks can have a lot more batteries turned on than fit in a
+// u64, so the digits are kept as they are, least significant first and without leading zeroes.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Jolts {
+    digits: Vec<u8>,
+}
+
+impl Jolts {
+    // From decimal digits, most significant first like they are in a bank
+    pub fn from_digits(digits: &[u32]) -> Jolts {
+        assert!(digits.iter().all(|d| *d < 10), "not a decimal digit");
+
+        let mut jolts = Jolts {
+            digits: digits.iter().rev().map(|d| *d as u8).collect(),
+        };
+        jolts.trim();
+        jolts
+    }
+
+    fn trim(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+}
+
+impl From<u64> for Jolts {
+    fn from(mut value: u64) -> Jolts {
+        let mut digits = Vec::new();
+
+        while value > 0 {
+            digits.push((value % 10) as u8);
+            value /= 10;
+        }
+
+        Jolts { digits }
+    }
+}
+
+impl AddAssign<&Jolts> for Jolts {
+    fn add_assign(&mut self, other: &Jolts) {
+        let mut carry = 0;
+
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            if i == self.digits.len() {
+                self.digits.push(0);
+            }
+
+            let sum = self.digits[i] + other.digits.get(i).copied().unwrap_or(0) + carry;
+            self.digits[i] = sum % 10;
+            carry = sum / 10;
+        }
+
+        if carry > 0 {
+            self.digits.push(carry);
+        }
+    }
+}
+
+impl fmt::Display for Jolts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+
+        let digits: String = self
+            .digits
+            .iter()
+            .rev()
+            .map(|d| char::from(b'0' + d))
+            .collect();
+
+        f.pad(&digits)
+    }
+}
+
+// The digits are stored backwards, so a derived Debug would print 987 as [7, 8, 9]
+impl fmt::Debug for Jolts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_digits_and_u64() {
+        assert_eq!(Jolts::from_digits(&[9, 8]), Jolts::from(98));
+        assert_eq!(Jolts::from_digits(&[0, 0, 7]), Jolts::from(7));
+        assert_eq!(Jolts::from_digits(&[0, 0]), Jolts::from(0));
+        assert_eq!(Jolts::from_digits(&[]).to_string(), "0");
+        assert_eq!(Jolts::from(u64::MAX).to_string(), u64::MAX.to_string());
+    }
+
+    #[test]
+    fn debug_is_the_decimal_value() {
+        assert_eq!(format!("{:?}", Jolts::from(987)), "987");
+        assert_eq!(format!("{:?}", Jolts::default()), "0");
+        assert_eq!(
+            format!("{:?}", Some(Jolts::from_digits(&[1, 2, 3]))),
+            "Some(123)"
+        );
+    }
+
+    #[test]
+    fn addition_carries() {
+        let mut jolts = Jolts::from(999);
+        jolts += &Jolts::from(1);
+        assert_eq!(jolts, Jolts::from(1000));
+
+        let mut jolts = Jolts::from(5);
+        jolts += &Jolts::from(99995);
+        assert_eq!(jolts.to_string(), "100000");
+
+        // Past what a u64 or u128 can hold
+        let mut jolts = Jolts::from_digits(&[9; 40]);
+        jolts += &Jolts::from(1);
+        assert_eq!(jolts.to_string(), format!("1{}", "0".repeat(40)));
+
+        let mut jolts = Jolts::default();
+        jolts += &Jolts::default();
+        assert_eq!(jolts.to_string(), "0");
+    }
+}
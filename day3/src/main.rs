@@ -20,108 +20,143 @@
 //
 //
 
-fn find_highest_digit(digit_string: &str, verbose: bool) -> Option<(usize, u32)> {
-    let mut highest_index = 0;
-    let mut highest: u32 = 0;
+mod bank;
+mod jolts;
 
-    for (i, c) in digit_string[0..digit_string.len()].chars().enumerate() {
-        let d = c.to_digit(10)?;
+use bank::{Bank, BankError, Validation, parse_banks};
+use jolts::Jolts;
 
-        if d > highest {
-            if verbose {
-                println!("digit is {}, higher than {}", d, highest);
-            }
-
-            highest = d;
-            highest_index = i;
-        }
-    }
-
-    Some((highest_index, highest))
+// The batteries turned on in a bank, as indices into it in order, and the joltage they give
+#[derive(Debug, Clone, PartialEq)]
+struct Joltage {
+    joltage: Jolts,
+    chosen: Vec<usize>,
 }
 
-fn find_max_joltage_part_1(data: &str, verbose: bool) -> Option<u64> {
-    let left_idx;
-    let left_digit;
-    let right_digit;
-
-    if let Some(left) = find_highest_digit(&data[..data.len() - 1], false) {
-        (left_idx, left_digit) = left;
-    } else {
-        if verbose {
-            println!("error parsing digit");
-        }
+// Largest joltage from turning on k batteries of the bank, in the order they are in. Goes through the
+// bank once, keeping a stack of the batteries chosen so far: a digit pushes out the smaller ones
+// before it as long as enough batteries are left after it to still make k. None if the bank has
+// fewer than k batteries.
+fn max_joltage(bank: &Bank, k: usize) -> Option<Joltage> {
+    if k > bank.len() {
         return None;
     }
 
-    if let Some(right) = find_highest_digit(&data[(left_idx + 1_usize)..], false) {
-        right_digit = right.1;
-    } else {
-        if verbose {
-            println!("error parsing digit");
+    let digits = bank.batteries();
+    let mut can_drop = bank.len() - k;
+    let mut chosen: Vec<usize> = Vec::with_capacity(bank.len());
+
+    for (i, &d) in digits.iter().enumerate() {
+        while can_drop > 0 && chosen.last().is_some_and(|&last| digits[last] < d) {
+            chosen.pop();
+            can_drop -= 1;
         }
-        return None;
+
+        chosen.push(i);
     }
 
+    chosen.truncate(k);
+
+    let chosen_digits: Vec<u32> = chosen.iter().map(|&i| digits[i]).collect();
+    let joltage = Jolts::from_digits(&chosen_digits);
+
+    Some(Joltage { joltage, chosen })
+}
+
+fn find_max_joltage_part_1(bank: &Bank, verbose: bool) -> Option<Joltage> {
+    let joltage = max_joltage(bank, 2);
+
     if verbose {
-        println!(
-            "from {}, largest joltage is {}{}",
-            data, left_digit, right_digit
-        );
+        print_choice(bank, 2, &joltage);
     }
 
-    Some(10 * left_digit as u64 + right_digit as u64)
+    joltage
 }
 
-fn find_max_joltage_part_2(digit_string: &str, verbose: bool) -> Option<u64> {
-    let digits = digit_string.len();
-    let mut digits_to_choose = 12;
+fn find_max_joltage_part_2(bank: &Bank, verbose: bool) -> Option<Joltage> {
+    let joltage = max_joltage(bank, 12);
 
-    let mut joltage = 0;
+    if verbose {
+        print_choice(bank, 12, &joltage);
+    }
 
-    let mut old_idx = 0;
+    joltage
+}
 
-    if verbose {
-        println!("Digits: {}", digit_string);
+fn print_choice(bank: &Bank, k: usize, joltage: &Option<Joltage>) {
+    match joltage {
+        Some(joltage) => println!(
+            "from {}, largest joltage is {} from batteries {:?}",
+            bank, joltage.joltage, joltage.chosen
+        ),
+        None => println!("bank {} has fewer than {} batteries", bank, k),
     }
+}
 
-    while digits_to_choose > 0 {
-        let working_str = &digit_string[old_idx..(digits + 1 - digits_to_choose)];
+// The bank with the batteries that are turned on in bold, like the puzzle text shows them
+fn highlight(bank: &Bank, chosen: &[usize]) -> String {
+    let mut highlighted = String::new();
 
-        let (highest_idx, highest) = find_highest_digit(&working_str, false)?;
-        if verbose {
-            println!(
-                "Digits to choose: {}, currently evlauating {}. Chose: {}",
-                digits_to_choose, working_str, highest
-            );
+    for (i, c) in bank.to_string().chars().enumerate() {
+        if chosen.contains(&i) {
+            highlighted.push_str(&format!("\x1b[1m{}\x1b[0m", c));
+        } else {
+            highlighted.push(c);
         }
-
-        joltage += 10_u64.pow(digits_to_choose as u32 - 1) * highest as u64;
-        digits_to_choose -= 1;
-        old_idx += highest_idx + 1;
     }
-    Some(joltage)
+
+    highlighted
+}
+
+// Total joltage of the banks, along with the banks left out of it
+#[derive(Debug, Clone, PartialEq)]
+struct TotalJoltage {
+    joltage: Jolts,
+    skipped: Vec<BankError>,
 }
 
-fn get_total_joltage(data: &str, joltage_calculator: JoltageCalculator, verbose: bool) -> u64 {
-    let mut sum = 0;
+// A bad bank, or one with too few batteries for the calculator, is an error when the validation
+// is strict. Lenient validation leaves it out of the total and lists it in skipped instead.
+fn get_total_joltage<F>(
+    data: &str,
+    joltage_calculator: F,
+    validation: Validation,
+    verbose: bool,
+) -> Result<TotalJoltage, BankError>
+where
+    F: Fn(&Bank, bool) -> Option<Joltage>,
+{
+    let parsed = parse_banks(data, validation)?;
+    let mut total = TotalJoltage {
+        joltage: Jolts::default(),
+        skipped: parsed.skipped,
+    };
 
-    for line in data.lines() {
-        if line.is_empty() {
+    for bank in &parsed.banks {
+        if let Some(joltage) = joltage_calculator(bank, verbose) {
+            total.joltage += &joltage.joltage;
             continue;
         }
 
-        if let Some(joltage) = joltage_calculator(line, verbose) {
-            sum += joltage;
-        } else if verbose {
-            println!("Error in find max joltage functoin returned 'None'");
+        // Just past the last battery, where the missing ones would be
+        let err = BankError {
+            line: bank.line,
+            column: bank.len() + 1,
+            reason: format!("not enough batteries in a bank of {}", bank.len()),
+        };
+
+        match validation {
+            Validation::Strict => return Err(err),
+            Validation::Lenient => total.skipped.push(err),
         }
     }
 
-    sum
+    total.skipped.sort_by_key(|err| err.line);
+
+    Ok(total)
 }
 
-type JoltageCalculator = fn(&str, bool) -> Option<u64>;
+type JoltageCalculator = fn(&Bank, bool) -> Option<Joltage>;
 
 fn main() {
     let input = if let Ok(file) = std::fs::read_to_string("data/input") {
@@ -131,52 +166,251 @@ fn main() {
         return;
     };
 
-    let joltage_part_1 = get_total_joltage(&input, find_max_joltage_part_1, false);
-    println!("Joltage part 1: {}", joltage_part_1);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Bad banks stop the run unless --lenient, then they are left out and listed
+    let validation = if args.iter().any(|arg| arg == "--lenient") {
+        Validation::Lenient
+    } else {
+        Validation::Strict
+    };
+
+    // Every bank with the batteries turned on for both parts, --show
+    if args.iter().any(|arg| arg == "--show") {
+        let parsed = match parse_banks(&input, validation) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("error reading banks: {}", err);
+                return;
+            }
+        };
+
+        for err in &parsed.skipped {
+            println!("skipped bank at {}", err);
+        }
+
+        for bank in &parsed.banks {
+            for (part, calculator) in [
+                (1, find_max_joltage_part_1 as JoltageCalculator),
+                (2, find_max_joltage_part_2),
+            ] {
+                match calculator(bank, false) {
+                    Some(joltage) => println!(
+                        "part {}: {} -> {}",
+                        part,
+                        highlight(bank, &joltage.chosen),
+                        joltage.joltage
+                    ),
+                    None => println!("part {}: {} -> no joltage", part, bank),
+                }
+            }
+        }
+        return;
+    }
+
+    let print_total = |name: &str, total: Result<TotalJoltage, BankError>| match total {
+        Ok(total) => {
+            for err in &total.skipped {
+                println!("skipped bank at {}", err);
+            }
+            println!("{}: {}", name, total.joltage);
+        }
+        Err(err) => println!("error in bank at {}", err),
+    };
+
+    // Any number of batteries per bank, e.g. --batteries=50
+    if let Some(batteries) = args.iter().find_map(|arg| arg.strip_prefix("--batteries=")) {
+        match batteries.parse::<usize>() {
+            Ok(k) => print_total(
+                &format!("Joltage with {} batteries", k),
+                get_total_joltage(&input, |bank, _| max_joltage(bank, k), validation, false),
+            ),
+            Err(err) => println!("error parsing battery count: {}", err),
+        }
+        return;
+    }
 
-    let joltage_part_2 = get_total_joltage(&input, find_max_joltage_part_2, false);
-    println!("Joltage part 2: {}", joltage_part_2);
+    print_total(
+        "Joltage part 1",
+        get_total_joltage(&input, find_max_joltage_part_1, validation, false),
+    );
+    print_total(
+        "Joltage part 2",
+        get_total_joltage(&input, find_max_joltage_part_2, validation, false),
+    );
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn bank(text: &str) -> Bank {
+        Bank::parse(text, 1).unwrap()
+    }
+
+    fn joltage(text: &str, k: usize) -> Option<Jolts> {
+        max_joltage(&bank(text), k).map(|joltage| joltage.joltage)
+    }
+
     #[test]
-    fn test_highest_digit() {
-        let res1 = find_highest_digit("987654321111111", true).unwrap();
-        assert_eq!(res1, (0 as usize, 9 as u32));
+    fn test_max_joltage() {
+        assert_eq!(joltage("987654321111111", 1), Some(Jolts::from(9)));
+        assert_eq!(joltage("234234234234278", 1), Some(Jolts::from(8)));
+        assert_eq!(joltage("811111111111119", 2), Some(Jolts::from(89)));
+        assert_eq!(joltage("234234234234278", 2), Some(Jolts::from(78)));
+        assert_eq!(
+            joltage("818181911112111", 12),
+            Some(Jolts::from(888911112111))
+        );
 
-        let res2 = find_highest_digit("234234234234278", true).unwrap();
-        assert_eq!(res2, (14 as usize, 8 as u32));
+        assert_eq!(joltage("12345", 5), Some(Jolts::from(12345)));
+        assert_eq!(joltage("12345", 0), Some(Jolts::from(0)));
+        assert_eq!(joltage("12345", 6), None);
+        assert_eq!(joltage("1", 2), None);
+    }
+
+    #[test]
+    fn test_max_joltage_matches_every_choice() {
+        let banks = ["818181911112111", "1119991", "54321", "3", "9876598765"];
+
+        for bank in banks {
+            let digits: Vec<u64> = bank
+                .chars()
+                .map(|c| c.to_digit(10).unwrap() as u64)
+                .collect();
+
+            for k in 0..=bank.len() {
+                // Every way of turning on k batteries, as a bit mask
+                let best = (0_u32..1 << bank.len())
+                    .filter(|mask| mask.count_ones() as usize == k)
+                    .map(|mask| {
+                        (0..bank.len())
+                            .filter(|i| mask & (1 << i) != 0)
+                            .fold(0, |joltage, i| joltage * 10 + digits[i])
+                    })
+                    .max();
+
+                assert_eq!(joltage(bank, k), best.map(Jolts::from));
+            }
+        }
+    }
+
+    #[test]
+    fn test_more_digits_than_a_u64() {
+        let text = "818181911112111".repeat(10);
+
+        // The 9s of the first four copies, then everything after the fourth one
+        assert_eq!(
+            joltage(&text, 100).unwrap().to_string(),
+            format!("9999112111{}", "818181911112111".repeat(6))
+        );
+        assert_eq!(joltage(&text, 150).unwrap().to_string(), text);
+
+        let banks = format!("{}\n{}\n", text, text);
+        let total = get_total_joltage(
+            &banks,
+            |bank, _| max_joltage(bank, 150),
+            Validation::Strict,
+            false,
+        )
+        .unwrap();
+        let mut expected = joltage(&text, 150).unwrap();
+        expected += &joltage(&text, 150).unwrap();
+        assert_eq!(total.joltage, expected);
+        assert_eq!(total.joltage.to_string().len(), 151);
+    }
+
+    #[test]
+    fn test_chosen_batteries() {
+        // The examples from the puzzle text
+        let chosen = |text: &str| max_joltage(&bank(text), 2).unwrap().chosen;
+
+        assert_eq!(chosen("987654321111111"), [0, 1]);
+        assert_eq!(chosen("811111111111119"), [0, 14]);
+        assert_eq!(chosen("234234234234278"), [13, 14]);
+        assert_eq!(chosen("818181911112111"), [6, 11]);
+
+        assert_eq!(
+            max_joltage(&bank("234234234234278"), 12).unwrap().chosen,
+            [2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+        );
+        assert_eq!(
+            highlight(&bank("12345"), &[1, 3]),
+            "1\x1b[1m2\x1b[0m3\x1b[1m4\x1b[0m5"
+        );
     }
 
     #[test]
     fn test_find_max_joltage_part_1() {
-        assert_eq!(find_max_joltage_part_1("818181911112111", true), Some(92));
+        assert_eq!(
+            find_max_joltage_part_1(&bank("818181911112111"), true).map(|j| j.joltage),
+            Some(Jolts::from(92))
+        );
+        // A single battery used to panic
+        assert_eq!(find_max_joltage_part_1(&bank("7"), true), None);
     }
 
     #[test]
     fn test_sample_input_part_1() {
         let input = std::fs::read_to_string("data/sample_input").unwrap();
-        let res = get_total_joltage(&input, find_max_joltage_part_1, true);
+        let res = get_total_joltage(&input, find_max_joltage_part_1, Validation::Strict, true);
 
-        assert_eq!(res, 357);
+        assert_eq!(res.unwrap().joltage, Jolts::from(357));
     }
 
     #[test]
     fn test_find_max_joltage_part_2() {
         assert_eq!(
-            find_max_joltage_part_2("234234234234278", true).unwrap(),
-            434234234278
+            find_max_joltage_part_2(&bank("234234234234278"), true)
+                .unwrap()
+                .joltage,
+            Jolts::from(434234234278)
         );
     }
 
     #[test]
     fn test_sample_input_part_2() {
         let input = std::fs::read_to_string("data/sample_input").unwrap();
-        let res = get_total_joltage(&input, find_max_joltage_part_2, true);
+        let res = get_total_joltage(&input, find_max_joltage_part_2, Validation::Strict, true);
+
+        assert_eq!(res.unwrap().joltage, Jolts::from(3121910778619));
+    }
+
+    #[test]
+    fn test_bad_banks() {
+        let input = "987654321111111\n81818x911112111\n7\n234234234234278\n";
+
+        // A stray character no longer just lowers the total
+        let err = get_total_joltage(input, find_max_joltage_part_1, Validation::Strict, false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 6: 'x' is not a joltage rating from 1 to 9"
+        );
+
+        let total =
+            get_total_joltage(input, find_max_joltage_part_1, Validation::Lenient, false).unwrap();
+        assert_eq!(total.joltage, Jolts::from(98 + 78));
+        assert_eq!(
+            total
+                .skipped
+                .iter()
+                .map(|err| (err.line, err.column))
+                .collect::<Vec<_>>(),
+            [(2, 6), (3, 2)]
+        );
+        assert_eq!(
+            total.skipped[1].reason,
+            "not enough batteries in a bank of 1"
+        );
 
-        assert_eq!(res, 3121910778619);
+        let err = get_total_joltage(
+            "987\n1\n",
+            find_max_joltage_part_1,
+            Validation::Strict,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!((err.line, err.column), (2, 2));
     }
 }